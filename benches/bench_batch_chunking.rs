@@ -0,0 +1,42 @@
+use criterion::{BatchSize, BenchmarkId, Criterion, criterion_group, criterion_main};
+use spellcheck::SpellCorrector;
+
+fn bench_batch_chunking(c: &mut Criterion) {
+    let dict_file = "words_100k.txt".to_string();
+    let max_edit_distance = 2;
+    let max_suggestions = 5;
+
+    // skewed batch: mostly short words with an occasional long, expensive one
+    let words: Vec<String> = (0..2000)
+        .map(|i| {
+            if i % 100 == 0 {
+                "internationalisationn".to_string()
+            } else {
+                ["teh", "cat", "wrds", "adn"][i % 4].to_string()
+            }
+        })
+        .collect();
+
+    let spell_corrector = SpellCorrector::from_word_list_file(&dict_file, max_edit_distance);
+
+    let mut group = c.benchmark_group("batch_chunking");
+    group.sample_size(10);
+    for min_len in [1, 16, 256] {
+        group.bench_with_input(
+            BenchmarkId::from_parameter(min_len),
+            &min_len,
+            |b, &min_len| {
+                // clones start with empty caches, so every run does the full lookups
+                b.iter_batched(
+                    || spell_corrector.clone().with_min_batch_len(min_len),
+                    |corrector| corrector.suggest_word_corrections(&words, max_suggestions),
+                    BatchSize::LargeInput,
+                )
+            },
+        );
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_batch_chunking);
+criterion_main!(benches);
@@ -0,0 +1,27 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use spellcheck::SpellCorrector;
+use std::fs;
+
+fn bench_build_index(c: &mut Criterion) {
+    let dict_file = "words_100k.txt".to_string();
+    let max_edit_distance = 2;
+
+    let content = fs::read_to_string(&dict_file).expect("Unable to read dictionary file");
+    let dictionary: Vec<String> = content.lines().map(|s| s.to_lowercase()).collect();
+
+    let (_, stats) = SpellCorrector::build_with_stats(dictionary.clone(), max_edit_distance);
+    println!(
+        "Indexed {} words: {} deletion keys, {} entries in {:?}",
+        stats.words, stats.deletion_keys, stats.entries, stats.elapsed
+    );
+
+    let mut group = c.benchmark_group("build_index");
+    group.sample_size(10);
+    group.bench_function("build_with_stats", |b| {
+        b.iter(|| SpellCorrector::build_with_stats(dictionary.clone(), max_edit_distance).1)
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_build_index);
+criterion_main!(benches);
@@ -0,0 +1,31 @@
+use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
+use spellcheck::SpellCorrector;
+
+fn bench_cache_mode(c: &mut Criterion) {
+    let dict_file = "words_100k.txt".to_string();
+    let max_edit_distance = 2;
+    let max_suggestions = 5;
+
+    // a small set of typos repeated many times, so most queries are cache hits
+    let typos = ["teh", "wrds", "adn", "recieve", "seperate", "definately"];
+    let words: Vec<String> = (0..20000)
+        .map(|i| typos[i % typos.len()].to_string())
+        .collect();
+
+    let spell_corrector = SpellCorrector::from_word_list_file(&dict_file, max_edit_distance);
+
+    let mut group = c.benchmark_group("cache_mode");
+    group.sample_size(10);
+    for per_thread in [false, true] {
+        let corrector = spell_corrector.clone().with_per_thread_cache(per_thread);
+        let name = if per_thread { "per_thread" } else { "shared" };
+        // the caches stay warm across iterations, measuring hit cost
+        group.bench_function(BenchmarkId::from_parameter(name), |b| {
+            b.iter(|| corrector.suggest_word_corrections(&words, max_suggestions))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_cache_mode);
+criterion_main!(benches);
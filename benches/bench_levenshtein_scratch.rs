@@ -0,0 +1,55 @@
+use criterion::{BatchSize, Criterion, criterion_group, criterion_main};
+use spellcheck::{SpellCorrector, bounded_edit_distance, prewarm_levenshtein_scratch};
+
+// Longer than any word in the benchmark dictionary or query set.
+const MAX_WORD_LEN: usize = 64;
+
+fn bench_levenshtein_scratch(c: &mut Criterion) {
+    let dict_file = "words_100k.txt".to_string();
+    let max_edit_distance = 2;
+    let max_suggestions = 5;
+
+    let pairs = [
+        ("speling", "spelling"),
+        ("recieve", "receive"),
+        ("functionalty", "functionality"),
+        (
+            "antidisestablishmentarianism",
+            "antidisestablishmentarianisms",
+        ),
+    ];
+
+    // size the scratch rows up front so the first long pair does not grow them
+    // inside the timed loop
+    prewarm_levenshtein_scratch(MAX_WORD_LEN);
+    c.bench_function("bounded_edit_distance_prewarmed", |b| {
+        b.iter(|| {
+            for (a, target) in pairs {
+                let _ = bounded_edit_distance(a.as_bytes(), target.as_bytes(), max_edit_distance);
+            }
+        })
+    });
+
+    let spell_corrector = SpellCorrector::from_word_list_file(&dict_file, max_edit_distance);
+    let words: Vec<String> = (0..2000)
+        .map(|i| pairs[i % pairs.len()].0.to_string())
+        .collect();
+    // batch queries verify candidates on rayon's threads, which each have
+    // their own scratch
+    rayon::broadcast(|_| prewarm_levenshtein_scratch(MAX_WORD_LEN));
+    let mut group = c.benchmark_group("levenshtein_scratch");
+    group.sample_size(10);
+    group.bench_function("batch_prewarmed", |b| {
+        // a fresh clone per iteration, outside the timing, so queries miss the
+        // suggestion cache and reach the distance computation
+        b.iter_batched(
+            || spell_corrector.clone(),
+            |corrector| corrector.suggest_word_corrections(&words, max_suggestions),
+            BatchSize::LargeInput,
+        )
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_levenshtein_scratch);
+criterion_main!(benches);
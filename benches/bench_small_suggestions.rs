@@ -0,0 +1,37 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use spellcheck::SpellCorrector;
+
+fn bench_small_suggestions(c: &mut Criterion) {
+    let dict_file = "words_100k.txt".to_string();
+    let max_edit_distance = 2;
+    let max_suggestions = 5;
+
+    let spell_corrector = SpellCorrector::from_word_list_file(&dict_file, max_edit_distance);
+    let words = ["sntence", "wrds", "functionalty", "speling", "checker"];
+
+    // warm the cache so both variants measure result construction, not lookup
+    for word in words {
+        let _ = spell_corrector.suggest_single_word_corrections(word, max_suggestions);
+    }
+
+    let mut group = c.benchmark_group("cached_suggestions");
+    group.bench_function("vec", |b| {
+        b.iter(|| {
+            for word in words {
+                let _ = spell_corrector.suggest_single_word_corrections(word, max_suggestions);
+            }
+        })
+    });
+    group.bench_function("smallvec", |b| {
+        b.iter(|| {
+            for word in words {
+                let _ =
+                    spell_corrector.suggest_single_word_corrections_small(word, max_suggestions);
+            }
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_small_suggestions);
+criterion_main!(benches);
@@ -0,0 +1,37 @@
+use spellcheck::{SpellCorrector, WhitespaceMode};
+use std::{env, io, path::Path};
+
+const MAX_EDIT_DISTANCE: usize = 2;
+
+// Usage: correct_file [input] [output] [dictionary]
+// Writes to stdout when no output path is given.
+fn main() -> io::Result<()> {
+    let mut args = env::args().skip(1);
+    let input_path = args
+        .next()
+        .unwrap_or_else(|| "examples/sample_input.txt".into());
+    let output_path = args.next();
+    let dict_path = args.next().unwrap_or_else(|| "words_100k.txt".into());
+
+    for path in [&input_path, &dict_path] {
+        if !Path::new(path).exists() {
+            eprintln!("File not found: {}", path);
+            std::process::exit(1);
+        }
+    }
+
+    let corrector = SpellCorrector::from_word_list_file(&dict_path, MAX_EDIT_DISTANCE)
+        .with_whitespace_mode(WhitespaceMode::Preserve);
+
+    match output_path {
+        Some(output_path) => {
+            corrector.correct_file(&input_path, &output_path)?;
+            eprintln!("Corrected {} -> {}", input_path, output_path);
+        }
+        None => {
+            let reader = io::BufReader::new(std::fs::File::open(&input_path)?);
+            corrector.correct_stream(reader, io::stdout().lock())?;
+        }
+    }
+    Ok(())
+}
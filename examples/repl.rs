@@ -18,10 +18,10 @@ fn main() -> io::Result<()> {
         std::process::exit(1);
     }
 
-    let corrector = SpellCorrector::from_word_list_file(&dict_path, MAX_EDIT_DISTANCE);
+    let mut corrector = SpellCorrector::from_word_list_file(&dict_path, MAX_EDIT_DISTANCE);
 
     println!(
-        "SymSpell REPL - dictionary: {}\n:type text, :q to quit",
+        "SymSpell REPL - dictionary: {}\n:type text, :q to quit; pick a suggestion by number, Enter to skip",
         dict_path
     );
     let mut input = String::new();
@@ -36,20 +36,48 @@ fn main() -> io::Result<()> {
             break;
         }
 
-        for token in input.split_whitespace() {
-            let word = token
-                .trim_matches(|c: char| !c.is_alphanumeric())
-                .to_lowercase();
-            if word.is_empty() {
+        let words: Vec<String> = input
+            .split_whitespace()
+            .map(|token| {
+                token
+                    .trim_matches(|c: char| !c.is_alphanumeric())
+                    .to_lowercase()
+            })
+            .filter(|word| !word.is_empty())
+            .collect();
+        for word in words {
+            let suggestions: Vec<String> =
+                match corrector.suggest_single_word_corrections(&word, MAX_SUGGESTIONS) {
+                    SuggestedCorrection::NoSuggestions => continue,
+                    SuggestedCorrection::Suggestions(list) => {
+                        list.into_iter().map(|s| s.word).collect()
+                    }
+                };
+            if suggestions.is_empty() {
+                println!("  {}  ->  (no suggestions)", word);
                 continue;
             }
+            let numbered: Vec<String> = suggestions
+                .iter()
+                .enumerate()
+                .map(|(i, s)| format!("{}) {}", i + 1, s))
+                .collect();
+            print!("  {}  ->  {}  choice: ", word, numbered.join("  "));
+            io::stdout().flush()?;
 
-            match corrector.suggest_single_word_corrections(&word, MAX_SUGGESTIONS) {
-                SuggestedCorrection::NoSuggestions => {}
-                SuggestedCorrection::Suggestions(list) => {
-                    let suggestions: Vec<_> = list.into_iter().map(|s| s.word).collect();
-                    println!("  {}  ->  {}", word, suggestions.join(", "));
-                }
+            let mut choice = String::new();
+            if io::stdin().read_line(&mut choice)? == 0 {
+                return Ok(()); // EOF
+            }
+            // accepted words rank first the next time this word comes up
+            if let Some(chosen) = choice
+                .trim()
+                .parse::<usize>()
+                .ok()
+                .and_then(|n| suggestions.get(n.wrapping_sub(1)))
+            {
+                corrector.record_acceptance(&word, chosen);
+                println!("  accepted {}", chosen);
             }
         }
     }
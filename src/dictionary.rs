@@ -0,0 +1,424 @@
+//! The word store behind a `SpellCorrector`.
+//!
+//! A corrector only needs to test membership, resolve word indices and look up
+//! which words share a deletion variant with the query, so any store that can
+//! answer those (a database, an FST, a custom on-disk format) can implement
+//! `Dictionary`. `InMemoryDictionary` is the default used by every built-in
+//! constructor; `HashedDictionary` trades a few spurious candidates for a
+//! much smaller index, and `FstDictionary` (feature `fst`) keeps the word
+//! set in a compact finite state transducer.
+use crate::spellcheck::deletion_variants;
+use std::borrow::Cow;
+use std::collections::{HashMap, HashSet};
+
+/// Read access to a dictionary and its deletion index.
+///
+/// Words are addressed by index in `0..len()`. `deletion_candidates(key)`
+/// must return every word that has `key` among its
+/// `deletion_variants(word, max_edit_distance, true)` for the corrector's
+/// `max_edit_distance`; words missing from it can never be suggested.
+pub trait Dictionary: Send + Sync {
+    /// Number of words.
+    fn len(&self) -> usize;
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether `word` is spelled correctly, i.e. is an exact entry.
+    fn contains(&self, word: &str) -> bool;
+
+    /// The word at `index`, or `None` if out of range.
+    fn get(&self, index: usize) -> Option<Cow<'_, str>>;
+
+    /// Indices of the words that have `del_key` as a deletion variant.
+    fn deletion_candidates(&self, del_key: &str) -> Cow<'_, [usize]>;
+
+    /// Corpus count of the word at `index`, used to rank suggestions at the
+    /// same distance. 0 means unknown.
+    fn frequency(&self, _index: usize) -> u64 {
+        0
+    }
+}
+
+/// The default dictionary: words and counts in vectors, a hash set for exact
+/// lookups and a hash map from deletion variant to word indices.
+#[derive(Debug, Clone, Default)]
+pub struct InMemoryDictionary {
+    pub(crate) words: Vec<String>,
+    pub(crate) frequencies: Vec<u64>, // parallel to words (0 = unknown)
+    pub(crate) lkp_dictionary: HashSet<String>, // for fast lookup
+    pub(crate) dictionary_del_mappings: HashMap<String, Vec<usize>>, // deletion edits -> word indices
+}
+
+impl InMemoryDictionary {
+    /// Indexes `words` for queries up to `max_edit_distance` edits away.
+    /// `frequencies` is parallel to `words`.
+    pub fn new(words: Vec<String>, frequencies: Vec<u64>, max_edit_distance: usize) -> Self {
+        let mut dictionary = InMemoryDictionary {
+            words,
+            frequencies,
+            ..Default::default()
+        };
+        dictionary.rebuild_index(max_edit_distance);
+        dictionary
+    }
+
+    // Wraps an existing deletion index, e.g. one loaded from disk.
+    pub(crate) fn from_parts(
+        words: Vec<String>,
+        frequencies: Vec<u64>,
+        dictionary_del_mappings: HashMap<String, Vec<usize>>,
+    ) -> Self {
+        InMemoryDictionary {
+            lkp_dictionary: words.iter().cloned().collect(),
+            words,
+            frequencies,
+            dictionary_del_mappings,
+        }
+    }
+
+    pub(crate) fn rebuild_index(&mut self, max_edit_distance: usize) {
+        self.rebuild_index_with(|_| max_edit_distance);
+    }
+
+    // Like `rebuild_index`, indexing the word at each index to the deletion
+    // depth `depth_for` gives it.
+    pub(crate) fn rebuild_index_with(&mut self, depth_for: impl Fn(usize) -> usize) {
+        self.dictionary_del_mappings = HashMap::new();
+        self.lkp_dictionary = HashSet::with_capacity(self.words.len());
+        for i in 0..self.words.len() {
+            self.index_word(i, depth_for(i));
+        }
+    }
+
+    // Adds `word`, or adds `frequency` to its count if it is already present;
+    // the unmodified word is always one of its own deletion keys.
+    pub(crate) fn push(&mut self, word: String, frequency: u64, max_edit_distance: usize) {
+        if self.lkp_dictionary.contains(&word) {
+            let existing = self.dictionary_del_mappings[&word]
+                .iter()
+                .copied()
+                .find(|&i| self.words[i] == word);
+            if let Some(index) = existing {
+                self.frequencies[index] = self.frequencies[index].saturating_add(frequency);
+                return;
+            }
+        }
+        self.words.push(word);
+        self.frequencies.push(frequency);
+        self.index_word(self.words.len() - 1, max_edit_distance);
+    }
+
+    fn index_word(&mut self, index: usize, max_edit_distance: usize) {
+        let word = &self.words[index];
+        for del_word in deletion_variants(word, max_edit_distance, true) {
+            self.dictionary_del_mappings
+                .entry(del_word)
+                .or_default()
+                .push(index);
+        }
+        self.lkp_dictionary.insert(word.clone());
+    }
+}
+
+impl Dictionary for InMemoryDictionary {
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.lkp_dictionary.contains(word)
+    }
+
+    fn get(&self, index: usize) -> Option<Cow<'_, str>> {
+        self.words.get(index).map(|w| Cow::Borrowed(w.as_str()))
+    }
+
+    fn deletion_candidates(&self, del_key: &str) -> Cow<'_, [usize]> {
+        match self.dictionary_del_mappings.get(del_key) {
+            Some(indices) => Cow::Borrowed(indices.as_slice()),
+            None => Cow::Borrowed(&[]),
+        }
+    }
+
+    fn frequency(&self, index: usize) -> u64 {
+        self.frequencies.get(index).copied().unwrap_or(0)
+    }
+}
+
+/// A dictionary whose deletion index is keyed by 64-bit hashes of the
+/// deletion variants instead of the variants themselves, which are the bulk
+/// of an `InMemoryDictionary`'s memory for large word lists.
+///
+/// Two variants with the same hash share a bucket, so a lookup may return
+/// words that do not actually have the key as a variant. The corrector
+/// verifies every candidate's distance anyway, so suggestions are the same
+/// as with `InMemoryDictionary`; collisions only cost a little extra
+/// verification. Build a corrector over it with `SpellCorrector::from_dictionary`.
+#[derive(Debug, Clone, Default)]
+pub struct HashedDictionary {
+    words: Vec<String>,
+    frequencies: Vec<u64>,             // parallel to words (0 = unknown)
+    buckets: HashMap<u64, Vec<usize>>, // deletion key hash -> word indices
+}
+
+impl HashedDictionary {
+    /// Indexes `words` for queries up to `max_edit_distance` edits away.
+    /// `frequencies` is parallel to `words`.
+    pub fn new(words: Vec<String>, frequencies: Vec<u64>, max_edit_distance: usize) -> Self {
+        let mut buckets: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (index, word) in words.iter().enumerate() {
+            for del_word in deletion_variants(word, max_edit_distance, true) {
+                let bucket = buckets.entry(key_hash(&del_word)).or_default();
+                // colliding variants of one word would repeat it
+                if bucket.last() != Some(&index) {
+                    bucket.push(index);
+                }
+            }
+        }
+        HashedDictionary {
+            words,
+            frequencies,
+            buckets,
+        }
+    }
+}
+
+// FNV-1a: fast on the short keys of a deletion index, and stable across
+// runs so builds are reproducible.
+fn key_hash(key: &str) -> u64 {
+    crate::index_file::fnv1a(key.bytes())
+}
+
+impl Dictionary for HashedDictionary {
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    // every word is its own deletion key, so its bucket holds it
+    fn contains(&self, word: &str) -> bool {
+        self.buckets
+            .get(&key_hash(word))
+            .is_some_and(|indices| indices.iter().any(|&i| self.words[i] == word))
+    }
+
+    fn get(&self, index: usize) -> Option<Cow<'_, str>> {
+        self.words.get(index).map(|w| Cow::Borrowed(w.as_str()))
+    }
+
+    fn deletion_candidates(&self, del_key: &str) -> Cow<'_, [usize]> {
+        match self.buckets.get(&key_hash(del_key)) {
+            Some(indices) => Cow::Borrowed(indices.as_slice()),
+            None => Cow::Borrowed(&[]),
+        }
+    }
+
+    fn frequency(&self, index: usize) -> u64 {
+        self.frequencies.get(index).copied().unwrap_or(0)
+    }
+}
+
+/// A read-only dictionary that answers `contains` from an `fst::Set`
+/// instead of `InMemoryDictionary`'s `HashSet<String>`, which holds a second
+/// copy of every word. The set shares prefixes and suffixes between words,
+/// so it takes a fraction of the memory while membership stays a walk of the
+/// word's bytes. The deletion index is the same as `InMemoryDictionary`'s.
+/// Build a corrector over it with `SpellCorrector::from_dictionary`.
+#[cfg(feature = "fst")]
+#[derive(Debug, Clone)]
+pub struct FstDictionary {
+    words: Vec<String>,
+    frequencies: Vec<u64>, // parallel to words (0 = unknown)
+    set: fst::Set<Vec<u8>>,
+    dictionary_del_mappings: HashMap<String, Vec<usize>>, // deletion edits -> word indices
+}
+
+#[cfg(feature = "fst")]
+impl FstDictionary {
+    /// Indexes `words` for queries up to `max_edit_distance` edits away.
+    /// `frequencies` is parallel to `words`.
+    pub fn new(words: Vec<String>, frequencies: Vec<u64>, max_edit_distance: usize) -> Self {
+        InMemoryDictionary::new(words, frequencies, max_edit_distance).into()
+    }
+}
+
+#[cfg(feature = "fst")]
+impl From<InMemoryDictionary> for FstDictionary {
+    fn from(dictionary: InMemoryDictionary) -> Self {
+        let mut sorted: Vec<&str> = dictionary.words.iter().map(String::as_str).collect();
+        sorted.sort_unstable();
+        sorted.dedup();
+        let set = fst::Set::from_iter(sorted).expect("keys are sorted and unique");
+        FstDictionary {
+            words: dictionary.words,
+            frequencies: dictionary.frequencies,
+            set,
+            dictionary_del_mappings: dictionary.dictionary_del_mappings,
+        }
+    }
+}
+
+#[cfg(feature = "fst")]
+impl Dictionary for FstDictionary {
+    fn len(&self) -> usize {
+        self.words.len()
+    }
+
+    fn contains(&self, word: &str) -> bool {
+        self.set.contains(word)
+    }
+
+    fn get(&self, index: usize) -> Option<Cow<'_, str>> {
+        self.words.get(index).map(|w| Cow::Borrowed(w.as_str()))
+    }
+
+    fn deletion_candidates(&self, del_key: &str) -> Cow<'_, [usize]> {
+        match self.dictionary_del_mappings.get(del_key) {
+            Some(indices) => Cow::Borrowed(indices.as_slice()),
+            None => Cow::Borrowed(&[]),
+        }
+    }
+
+    fn frequency(&self, index: usize) -> u64 {
+        self.frequencies.get(index).copied().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spellcheck::{SpellCorrector, SuggestedCorrection};
+
+    // Computes deletion candidates by scanning every word, like a store
+    // without a precomputed index might.
+    struct ScanDictionary {
+        words: Vec<&'static str>,
+        max_edit_distance: usize,
+    }
+
+    impl Dictionary for ScanDictionary {
+        fn len(&self) -> usize {
+            self.words.len()
+        }
+
+        fn contains(&self, word: &str) -> bool {
+            self.words.contains(&word)
+        }
+
+        fn get(&self, index: usize) -> Option<Cow<'_, str>> {
+            self.words.get(index).map(|w| Cow::Borrowed(*w))
+        }
+
+        fn deletion_candidates(&self, del_key: &str) -> Cow<'_, [usize]> {
+            let indices = (0..self.words.len())
+                .filter(|&i| {
+                    deletion_variants(self.words[i], self.max_edit_distance, true).contains(del_key)
+                })
+                .collect();
+            Cow::Owned(indices)
+        }
+    }
+
+    #[test]
+    fn test_custom_dictionary() {
+        let dictionary = ScanDictionary {
+            words: vec!["hello", "help", "world"],
+            max_edit_distance: 1,
+        };
+        let corrector = SpellCorrector::from_dictionary(dictionary, 1);
+        assert!(corrector.contains("world"));
+        match corrector.suggest_single_word_corrections("wrld", 3) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list.len(), 1);
+                assert_eq!(list[0].word, "world");
+                assert_eq!(list[0].frequency, 0);
+            }
+            _ => panic!("expected suggestions"),
+        }
+        // "help" and "hello" are both one edit away; without frequencies the
+        // one closest in length to the query wins
+        assert_eq!(corrector.correct_text("helo wrld"), "Help world");
+    }
+
+    #[test]
+    fn test_hashed_dictionary_matches_string_keys() {
+        let words: Vec<String> = [
+            "spelling", "spilling", "selling", "telling", "smelling", "spell", "spill", "café",
+            "cafe", "cave", "care", "core", "cure", "pure",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let frequencies: Vec<u64> = (0..words.len() as u64).collect();
+        let hashed = SpellCorrector::from_dictionary(
+            HashedDictionary::new(words.clone(), frequencies.clone(), 2),
+            2,
+        );
+        let pairs = words.iter().cloned().zip(frequencies).collect();
+        let keyed = SpellCorrector::new_with_frequencies(pairs, 2);
+        assert!(hashed.contains("café"));
+        assert!(!hashed.contains("caf"));
+
+        let summary = |result: SuggestedCorrection| match result {
+            SuggestedCorrection::Suggestions(list) => Some(
+                list.into_iter()
+                    .map(|s| (s.word, s.distance, s.frequency))
+                    .collect::<Vec<_>>(),
+            ),
+            SuggestedCorrection::NoSuggestions => None,
+        };
+        for query in [
+            "speling", "sellin", "cafe", "cre", "pur", "xyz", "spell", "smeling",
+        ] {
+            assert_eq!(
+                summary(hashed.suggest_single_word_corrections(query, 10)),
+                summary(keyed.suggest_single_word_corrections(query, 10)),
+                "mismatch for {}",
+                query
+            );
+        }
+    }
+
+    #[cfg(feature = "fst")]
+    #[test]
+    fn test_fst_dictionary_matches_hash_set() {
+        let words: Vec<String> = [
+            "a", "an", "and", "ant", "café", "cafe", "zebra", "zoo", "über", "naïve", "and",
+        ]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+        let frequencies = vec![1; words.len()];
+        let hashed = InMemoryDictionary::new(words.clone(), frequencies.clone(), 1);
+        let fst = FstDictionary::new(words.clone(), frequencies, 1);
+        let probes = ["", "A", "ands", "caf", "cafés", "zo", "uber", "naive", "zz"];
+        for word in words.iter().map(String::as_str).chain(probes) {
+            assert_eq!(fst.contains(word), hashed.contains(word), "{:?}", word);
+        }
+        assert!(words.iter().all(|w| fst.contains(w)));
+
+        let corrector = SpellCorrector::from_dictionary(fst, 1);
+        assert!(corrector.contains("über"));
+        assert_eq!(corrector.best_correction("zebr").unwrap().word, "zebra");
+    }
+
+    #[cfg(feature = "fst")]
+    #[test]
+    fn test_fst_dictionary_matches_hash_set_on_word_list() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/words_100k.txt");
+        let content = std::fs::read_to_string(path).expect("Unable to read word list");
+        let words: Vec<String> = content.lines().map(str::to_string).collect();
+        let frequencies = vec![1; words.len()];
+        let hashed = InMemoryDictionary::new(words.clone(), frequencies.clone(), 1);
+        let fst = FstDictionary::new(words.clone(), frequencies, 1);
+        // every word, plus a near miss on either side of it in key order
+        for word in &words {
+            assert!(fst.contains(word), "{:?}", word);
+            let shorter = &word[..word.char_indices().last().map_or(0, |(i, _)| i)];
+            for probe in [shorter, &format!("{}s", word), &format!("{}\u{0}", word)] {
+                assert_eq!(fst.contains(probe), hashed.contains(probe), "{:?}", probe);
+            }
+        }
+    }
+}
@@ -0,0 +1,584 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+
+/// Per-operation costs for `weighted_edit_distance`.
+///
+/// Costs are whole numbers so weighted distances can be compared against the
+/// corrector's `max_edit_distance` like ordinary edit counts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WeightedEditCosts {
+    pub insert: usize,
+    pub delete: usize,
+    pub substitute: usize,
+    pub transpose: usize, // swap of two adjacent characters
+}
+
+impl Default for WeightedEditCosts {
+    /// Every operation costs 1 (optimal string alignment distance).
+    fn default() -> Self {
+        WeightedEditCosts {
+            insert: 1,
+            delete: 1,
+            substitute: 1,
+            transpose: 1,
+        }
+    }
+}
+
+impl WeightedEditCosts {
+    /// OCR output mostly confuses one glyph for another, so substitutions are
+    /// cheap compared to the other operations.
+    pub fn ocr() -> Self {
+        WeightedEditCosts {
+            insert: 2,
+            delete: 2,
+            substitute: 1,
+            transpose: 2,
+        }
+    }
+
+    /// Typing errors often swap adjacent keys, so transpositions are cheap.
+    pub fn keyboard() -> Self {
+        WeightedEditCosts {
+            insert: 2,
+            delete: 2,
+            substitute: 2,
+            transpose: 1,
+        }
+    }
+}
+
+/// Weighted optimal string alignment distance between `a` and `b`, turning
+/// `a` into `b`. Works on chars, so multi-byte characters count once.
+///
+/// Returns `max_dist + 1` as soon as the distance is known to exceed
+/// `max_dist`.
+pub fn weighted_edit_distance(
+    a: &str,
+    b: &str,
+    costs: &WeightedEditCosts,
+    max_dist: usize,
+) -> usize {
+    let a: Vec<char> = a.chars().collect();
+    let b: Vec<char> = b.chars().collect();
+    let n = b.len();
+
+    let mut before_prev: Vec<usize> = vec![0; n + 1];
+    let mut prev: Vec<usize> = (0..=n).map(|j| j * costs.insert).collect();
+    let mut curr = vec![0; n + 1];
+
+    for i in 1..=a.len() {
+        curr[0] = i * costs.delete;
+        for j in 1..=n {
+            let sub = if a[i - 1] == b[j - 1] {
+                prev[j - 1]
+            } else {
+                prev[j - 1] + costs.substitute
+            };
+            let mut best = sub
+                .min(prev[j] + costs.delete)
+                .min(curr[j - 1] + costs.insert);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(before_prev[j - 2] + costs.transpose);
+            }
+            curr[j] = best;
+        }
+        // a transposition can still reach back to the previous row
+        if curr.iter().chain(prev.iter()).all(|&d| d > max_dist) {
+            return max_dist.saturating_add(1);
+        }
+        std::mem::swap(&mut before_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[n].min(max_dist.saturating_add(1))
+}
+
+/// Probabilities of single typing errors, the error model of noisy-channel
+/// ranking (see `SpellCorrector::with_noisy_channel`).
+///
+/// Each is the chance that one character of the intended word is typed with
+/// that error; matching characters are treated as certain, so only the
+/// relative sizes of the probabilities matter for ranking.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ErrorModel {
+    pub insert: f64,                               // an extra character typed
+    pub delete: f64,                               // a character left out
+    pub substitute: f64,                           // a wrong character typed
+    pub transpose: f64,                            // two adjacent characters swapped
+    pub substitutions: HashMap<(char, char), f64>, // (intended, typed) -> probability, overriding `substitute`
+}
+
+impl Default for ErrorModel {
+    /// Rates in the proportions reported for typing errors by Kernighan,
+    /// Church and Gale (1990): deletions and insertions a little more common
+    /// than substitutions, transpositions rarer, about one error per 50
+    /// characters in all.
+    fn default() -> Self {
+        ErrorModel {
+            insert: 0.006,
+            delete: 0.007,
+            substitute: 0.005,
+            transpose: 0.002,
+            substitutions: HashMap::new(),
+        }
+    }
+}
+
+/// Natural log of `P(typo | intended)` under `model`: the likeliest way to
+/// type `intended` as `typo` with insertions, deletions, substitutions and
+/// adjacent transpositions, each error contributing the log of its
+/// probability. 0.0 for identical strings; negative infinity if only errors
+/// of probability 0 could explain `typo`. Works on chars.
+pub fn channel_log_probability(typo: &str, intended: &str, model: &ErrorModel) -> f64 {
+    let a: Vec<char> = intended.chars().collect();
+    let b: Vec<char> = typo.chars().collect();
+    let n = b.len();
+    // costs are negative log probabilities, so the cheapest alignment wins
+    let cost = |p: f64| -p.ln();
+    let (insert, delete, transpose) = (
+        cost(model.insert),
+        cost(model.delete),
+        cost(model.transpose),
+    );
+    let substitute = |intended: char, typed: char| {
+        cost(
+            model
+                .substitutions
+                .get(&(intended, typed))
+                .copied()
+                .unwrap_or(model.substitute),
+        )
+    };
+
+    let mut before_prev: Vec<f64> = vec![0.0; n + 1];
+    let mut prev: Vec<f64> = (0..=n).map(|j| j as f64 * insert).collect();
+    let mut curr = vec![0.0; n + 1];
+    for i in 1..=a.len() {
+        curr[0] = i as f64 * delete;
+        for j in 1..=n {
+            let sub = if a[i - 1] == b[j - 1] {
+                prev[j - 1]
+            } else {
+                prev[j - 1] + substitute(a[i - 1], b[j - 1])
+            };
+            let mut best = sub.min(prev[j] + delete).min(curr[j - 1] + insert);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                best = best.min(before_prev[j - 2] + transpose);
+            }
+            curr[j] = best;
+        }
+        std::mem::swap(&mut before_prev, &mut prev);
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    -prev[n]
+}
+
+thread_local! {
+    // The two rows `bounded_edit_distance` fills, kept between calls so each
+    // thread allocates only when it meets a longer word than before.
+    static SCRATCH: RefCell<(Vec<usize>, Vec<usize>)> =
+        const { RefCell::new((Vec::new(), Vec::new())) };
+}
+
+/// Sizes this thread's `bounded_edit_distance` scratch rows for words of up
+/// to `max_word_len` items, so a benchmark's timed loop does not include
+/// their growth. Call it on every thread that will compute distances, e.g.
+/// through `rayon::broadcast`.
+pub fn prewarm_levenshtein_scratch(max_word_len: usize) {
+    SCRATCH.with_borrow_mut(|(prev, curr)| {
+        for row in [prev, curr] {
+            row.clear();
+            row.reserve(max_word_len + 1);
+        }
+    });
+}
+
+/// Levenshtein distance between two sequences of any comparable items:
+/// bytes, `char`s, tokens, ids. Pass `&[char]` to count multi-byte
+/// characters once.
+///
+/// Only cells within `max_dist` of the diagonal are computed, and the result
+/// is capped at `max_dist.saturating_add(1)`, meaning "further than `max_dist`".
+pub fn bounded_edit_distance<T: PartialEq>(a: &[T], b: &[T], max_dist: usize) -> usize {
+    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
+    if longer.len() - shorter.len() > max_dist {
+        return max_dist + 1;
+    }
+    // no distance exceeds the longer length, and a smaller bound keeps the
+    // band arithmetic below from overflowing for huge `max_dist`
+    let max_dist = max_dist.min(longer.len());
+
+    let n = longer.len();
+    SCRATCH.with_borrow_mut(|(prev, curr)| {
+        prev.clear();
+        prev.extend(0..=n);
+        curr.clear();
+        curr.resize(n + 1, 0);
+
+        for (i, sc) in shorter.iter().enumerate() {
+            let row = i + 1;
+            curr[0] = row;
+
+            let col_min = if row > max_dist { row - max_dist } else { 1 };
+            let col_max = (row + max_dist).min(n);
+
+            for j in 1..=n {
+                if j < col_min || j > col_max {
+                    curr[j] = max_dist + 1;
+                    continue;
+                }
+                let cost = if *sc == longer[j - 1] { 0 } else { 1 };
+                let ins = curr[j - 1] + 1;
+                let del = prev[j] + 1;
+                let sub = prev[j - 1] + cost;
+                curr[j] = ins.min(del).min(sub);
+            }
+            std::mem::swap(prev, curr);
+        }
+        prev[n].min(max_dist + 1)
+    })
+}
+
+/// One run of a character-level diff from a query to a suggestion, see
+/// `edit_diff`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DiffSpan {
+    Unchanged(String),
+    /// Present in the suggestion but not the query.
+    Inserted(String),
+    /// Present in the query but not the suggestion.
+    Deleted(String),
+    Substituted {
+        from: String,
+        to: String,
+    },
+}
+
+/// A minimal Levenshtein alignment of `from` to `to` as runs of unchanged,
+/// inserted, deleted and substituted characters, for rendering inline diffs.
+/// Concatenating the `from` sides of the spans gives `from`, and the `to`
+/// sides give `to`.
+pub fn edit_diff(from: &str, to: &str) -> Vec<DiffSpan> {
+    let a: Vec<char> = from.chars().collect();
+    let b: Vec<char> = to.chars().collect();
+    let mut dp = vec![vec![0usize; b.len() + 1]; a.len() + 1];
+    for (i, row) in dp.iter_mut().enumerate() {
+        row[0] = i;
+    }
+    for (j, cell) in dp[0].iter_mut().enumerate() {
+        *cell = j;
+    }
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let sub = dp[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]);
+            dp[i][j] = sub.min(dp[i - 1][j] + 1).min(dp[i][j - 1] + 1);
+        }
+    }
+
+    // walk back from the end, preferring matches and substitutions
+    let mut spans: Vec<DiffSpan> = Vec::new();
+    let (mut i, mut j) = (a.len(), b.len());
+    while i > 0 || j > 0 {
+        let span =
+            if i > 0 && j > 0 && dp[i][j] == dp[i - 1][j - 1] + usize::from(a[i - 1] != b[j - 1]) {
+                i -= 1;
+                j -= 1;
+                if a[i] == b[j] {
+                    DiffSpan::Unchanged(a[i].to_string())
+                } else {
+                    DiffSpan::Substituted {
+                        from: a[i].to_string(),
+                        to: b[j].to_string(),
+                    }
+                }
+            } else if i > 0 && dp[i][j] == dp[i - 1][j] + 1 {
+                i -= 1;
+                DiffSpan::Deleted(a[i].to_string())
+            } else {
+                j -= 1;
+                DiffSpan::Inserted(b[j].to_string())
+            };
+        // spans are built back to front, so prepend to the previous run
+        match (spans.last_mut(), span) {
+            (Some(DiffSpan::Unchanged(run)), DiffSpan::Unchanged(c))
+            | (Some(DiffSpan::Inserted(run)), DiffSpan::Inserted(c))
+            | (Some(DiffSpan::Deleted(run)), DiffSpan::Deleted(c)) => run.insert_str(0, &c),
+            (
+                Some(DiffSpan::Substituted { from, to }),
+                DiffSpan::Substituted { from: f, to: t },
+            ) => {
+                from.insert_str(0, &f);
+                to.insert_str(0, &t);
+            }
+            (_, span) => spans.push(span),
+        }
+    }
+    spans.reverse();
+    spans
+}
+
+/// Pattern character matching any single character in wildcard queries.
+pub(crate) const WILDCARD_ANY: char = '*';
+/// Pattern character matching one character or none in wildcard queries.
+pub(crate) const WILDCARD_OPTIONAL: char = '?';
+
+/// Levenshtein distance from `pattern` to `word` in which `WILDCARD_ANY`
+/// matches any one character for free and `WILDCARD_OPTIONAL` matches any one
+/// character or nothing for free. Works on chars; capped at `max_dist + 1`.
+pub(crate) fn wildcard_distance(pattern: &str, word: &str, max_dist: usize) -> usize {
+    let word: Vec<char> = word.chars().collect();
+    let n = word.len();
+    let mut prev: Vec<usize> = (0..=n).collect();
+    let mut curr = vec![0; n + 1];
+    for p in pattern.chars() {
+        let skip_cost = if p == WILDCARD_OPTIONAL { 0 } else { 1 };
+        curr[0] = prev[0] + skip_cost;
+        for j in 1..=n {
+            let matches = p == WILDCARD_ANY || p == WILDCARD_OPTIONAL || p == word[j - 1];
+            let sub = prev[j - 1] + usize::from(!matches);
+            curr[j] = sub.min(prev[j] + skip_cost).min(curr[j - 1] + 1);
+        }
+        if curr.iter().all(|&d| d > max_dist) {
+            return max_dist.saturating_add(1);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[n].min(max_dist.saturating_add(1))
+}
+
+/// How early in `from` the edits turning it into `to` fall: each edited
+/// char of `from` (and each insertion) at char position `i` of an `n`-char
+/// `from` adds `n + 1 - i`, so a changed first letter costs the most and an
+/// appended letter the least.
+pub(crate) fn edit_position_penalty(from: &str, to: &str) -> usize {
+    let n = from.chars().count();
+    let mut pos = 0;
+    let mut penalty = 0;
+    for span in edit_diff(from, to) {
+        match span {
+            DiffSpan::Unchanged(text) => pos += text.chars().count(),
+            DiffSpan::Inserted(text) => penalty += text.chars().count() * (n + 1 - pos),
+            DiffSpan::Deleted(text) | DiffSpan::Substituted { from: text, .. } => {
+                for _ in text.chars() {
+                    penalty += n + 1 - pos;
+                    pos += 1;
+                }
+            }
+        }
+    }
+    penalty
+}
+
+/// Length of the longest common subsequence of the chars of `a` and `b`.
+pub(crate) fn longest_common_subsequence(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut prev = vec![0; b.len() + 1];
+    let mut curr = vec![0; b.len() + 1];
+    for ca in a.chars() {
+        for (j, &cb) in b.iter().enumerate() {
+            curr[j + 1] = if ca == cb {
+                prev[j] + 1
+            } else {
+                curr[j].max(prev[j + 1])
+            };
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_weighted_unit_costs() {
+        let costs = WeightedEditCosts::default();
+        assert_eq!(weighted_edit_distance("kitten", "sitting", &costs, 5), 3);
+        assert_eq!(weighted_edit_distance("abc", "bac", &costs, 5), 1);
+        assert_eq!(weighted_edit_distance("", "abc", &costs, 5), 3);
+        assert_eq!(weighted_edit_distance("café", "cafe", &costs, 5), 1);
+        assert_eq!(weighted_edit_distance("kitten", "sitting", &costs, 2), 3);
+    }
+
+    #[test]
+    fn test_channel_log_probability() {
+        let model = ErrorModel::default();
+        assert_eq!(channel_log_probability("there", "there", &model), 0.0);
+        let swapped = channel_log_probability("teh", "the", &model);
+        assert!((swapped - model.transpose.ln()).abs() < 1e-9);
+        // two errors are less likely than one
+        assert!(
+            channel_log_probability("tha", "the", &model)
+                > channel_log_probability("ta", "the", &model) + model.insert.ln()
+        );
+
+        let mut fat_fingers = model.clone();
+        fat_fingers.substitutions.insert(('e', 'r'), 0.05);
+        assert!(
+            channel_log_probability("thr", "the", &fat_fingers)
+                > channel_log_probability("thr", "the", &model)
+        );
+    }
+
+    #[test]
+    fn test_weighted_profiles() {
+        let ocr = WeightedEditCosts::ocr();
+        let keyboard = WeightedEditCosts::keyboard();
+        // "bac" is a transposition away, "abd" a substitution away
+        assert_eq!(weighted_edit_distance("abc", "bac", &ocr, 4), 2);
+        assert_eq!(weighted_edit_distance("abc", "abd", &ocr, 4), 1);
+        assert_eq!(weighted_edit_distance("abc", "bac", &keyboard, 4), 1);
+        assert_eq!(weighted_edit_distance("abc", "abd", &keyboard, 4), 2);
+        assert_eq!(weighted_edit_distance("abc", "ab", &keyboard, 4), 2);
+    }
+
+    #[test]
+    fn test_bounded_edit_distance_chars() {
+        let chars = |s: &str| s.chars().collect::<Vec<char>>();
+        assert_eq!(bounded_edit_distance(&chars("café"), &chars("cafe"), 2), 1);
+        // bytes see two edits where chars see one
+        assert_eq!(bounded_edit_distance("café".as_bytes(), b"cafe", 2), 2);
+        assert_eq!(
+            bounded_edit_distance(&chars("kitten"), &chars("sitting"), 3),
+            3
+        );
+        assert_eq!(
+            bounded_edit_distance(&chars("kitten"), &chars("sitting"), 1),
+            2
+        );
+        assert_eq!(bounded_edit_distance(&chars(""), &chars("abc"), 5), 3);
+    }
+
+    #[test]
+    fn test_bounded_edit_distance_ids() {
+        let a: [u32; 4] = [7, 1, 4, 9];
+        assert_eq!(bounded_edit_distance(&a, &[7, 1, 4, 9], 2), 0);
+        assert_eq!(bounded_edit_distance(&a, &[7, 4, 9], 2), 1);
+        assert_eq!(bounded_edit_distance(&a, &[1, 7, 4, 8], 2), 3);
+        assert_eq!(bounded_edit_distance(&a, &[], 2), 3);
+    }
+
+    #[test]
+    fn test_wildcard_distance() {
+        assert_eq!(wildcard_distance("sp*lling", "spelling", 2), 0);
+        assert_eq!(wildcard_distance("sp*lling", "spllling", 2), 0);
+        assert_eq!(wildcard_distance("sp*lling", "spling", 2), 2);
+        assert_eq!(wildcard_distance("spell?", "spell", 2), 0);
+        assert_eq!(wildcard_distance("spell?", "spells", 2), 0);
+        assert_eq!(wildcard_distance("spell?", "spelled", 2), 1);
+        assert_eq!(wildcard_distance("**", "abc", 0), 1);
+    }
+
+    #[test]
+    fn test_edit_diff() {
+        use DiffSpan::*;
+        let s = |t: &str| t.to_string();
+        assert_eq!(edit_diff("abc", "abc"), vec![Unchanged(s("abc"))]);
+        assert_eq!(
+            edit_diff("kitten", "sitting"),
+            vec![
+                Substituted {
+                    from: s("k"),
+                    to: s("s")
+                },
+                Unchanged(s("itt")),
+                Substituted {
+                    from: s("e"),
+                    to: s("i")
+                },
+                Unchanged(s("n")),
+                Inserted(s("g")),
+            ]
+        );
+        assert_eq!(
+            edit_diff("naïve", "naive"),
+            vec![
+                Unchanged(s("na")),
+                Substituted {
+                    from: s("ï"),
+                    to: s("i")
+                },
+                Unchanged(s("ve"))
+            ]
+        );
+        assert_eq!(
+            edit_diff("hellxo", "hello"),
+            vec![Unchanged(s("hell")), Deleted(s("x")), Unchanged(s("o"))]
+        );
+        assert_eq!(edit_diff("", "ab"), vec![Inserted(s("ab"))]);
+    }
+
+    // full-matrix Levenshtein, the reference for the banded versions
+    fn reference_distance<T: PartialEq>(a: &[T], b: &[T]) -> usize {
+        let mut prev: Vec<usize> = (0..=b.len()).collect();
+        for (i, x) in a.iter().enumerate() {
+            let mut curr = vec![i + 1; b.len() + 1];
+            for (j, y) in b.iter().enumerate() {
+                curr[j + 1] = (prev[j] + usize::from(x != y))
+                    .min(prev[j + 1] + 1)
+                    .min(curr[j] + 1);
+            }
+            prev = curr;
+        }
+        prev[b.len()]
+    }
+
+    proptest! {
+        #[test]
+        fn prop_bounded_matches_reference(a in "[abcé]{0,10}", b in "[abcé]{0,10}", k in 0usize..6) {
+            let chars = |s: &str| s.chars().collect::<Vec<char>>();
+            let (ca, cb) = (chars(&a), chars(&b));
+            prop_assert_eq!(
+                bounded_edit_distance(&ca, &cb, k),
+                reference_distance(&ca, &cb).min(k + 1)
+            );
+            // bytes, where "é" is two units
+            prop_assert_eq!(
+                bounded_edit_distance(a.as_bytes(), b.as_bytes(), k),
+                reference_distance(a.as_bytes(), b.as_bytes()).min(k + 1)
+            );
+            prop_assert_eq!(
+                crate::spellcheck::bounded_levenshtein(&a, &b, k),
+                reference_distance(a.as_bytes(), b.as_bytes()).min(k + 1)
+            );
+        }
+    }
+
+    #[test]
+    fn test_bounded_edit_distance_regressions() {
+        // results past the bound are capped at `max_dist + 1`; the original
+        // banded DP returned whatever value the band held, possibly more
+        assert_eq!(bounded_edit_distance(b"aaa", b"bbb", 1), 2);
+        assert_eq!(bounded_edit_distance(b"abcd", b"dcba", 1), 2);
+        // a huge bound used to overflow the band arithmetic
+        assert_eq!(bounded_edit_distance(b"ab", b"abc", usize::MAX), 1);
+        assert_eq!(bounded_edit_distance(b"", b"", usize::MAX), 0);
+        let costs = WeightedEditCosts::default();
+        assert_eq!(weighted_edit_distance("ab", "ba", &costs, usize::MAX), 1);
+        assert_eq!(wildcard_distance("a*", "abc", usize::MAX), 1);
+    }
+
+    #[test]
+    fn test_edit_position_penalty() {
+        assert_eq!(edit_position_penalty("mat", "mat"), 0);
+        assert_eq!(edit_position_penalty("mat", "bat"), 4);
+        assert_eq!(edit_position_penalty("mat", "map"), 2);
+        assert_eq!(edit_position_penalty("mat", "mats"), 1);
+        assert_eq!(edit_position_penalty("mat", "at"), 4);
+    }
+
+    #[test]
+    fn test_prewarm_levenshtein_scratch() {
+        prewarm_levenshtein_scratch(64);
+        let capacity = SCRATCH.with_borrow(|(prev, curr)| prev.capacity().min(curr.capacity()));
+        assert!(capacity >= 65);
+        // reused rows give the same answers as fresh ones, long word or short
+        let long = "a".repeat(40);
+        assert_eq!(bounded_edit_distance(long.as_bytes(), b"aaa", 50), 37);
+        assert_eq!(bounded_edit_distance(b"kitten", b"sitting", 3), 3);
+        assert_eq!(bounded_edit_distance(b"kitten", b"sitting", 1), 2);
+        assert_eq!(bounded_edit_distance(b"", b"", 0), 0);
+    }
+}
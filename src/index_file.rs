@@ -0,0 +1,147 @@
+//! Standalone binary files holding just the deletion index, so a prebuilt
+//! index can be shared between machines that already have the word list.
+//!
+//! Layout (little-endian): an 8-byte magic, `max_edit_distance: u32`,
+//! `n_words: u32`, a 64-bit FNV-1a fingerprint of the dictionary, `n_keys:
+//! u32`, then per key `key_len: u32`, the UTF-8 key, `n_postings: u32` and the
+//! postings as `u32` word indices.
+use std::collections::HashMap;
+use std::fs;
+
+const MAGIC: &[u8; 8] = b"SPCKIDX1";
+
+type BoxError = Box<dyn std::error::Error>;
+
+/// 64-bit FNV-1a: fast on short inputs and stable across runs and
+/// platforms. Used for index file fingerprints and `HashedDictionary` keys.
+pub(crate) fn fnv1a(bytes: impl IntoIterator<Item = u8>) -> u64 {
+    bytes.into_iter().fold(0xcbf29ce484222325, |hash, byte| {
+        (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+    })
+}
+
+/// FNV-1a over the words and their order; cheap, stable across runs and
+/// platforms, and good enough to catch an index built for another word list.
+pub(crate) fn dictionary_fingerprint(dictionary: &[String]) -> u64 {
+    fnv1a(
+        dictionary
+            .iter()
+            .flat_map(|word| word.bytes().chain([0xff])),
+    )
+}
+
+pub(crate) fn write_index(
+    dictionary: &[String],
+    dictionary_del_mappings: &HashMap<String, Vec<usize>>,
+    max_edit_distance: usize,
+    file_path: &str,
+) -> Result<(), BoxError> {
+    fn put_u32(out: &mut Vec<u8>, n: usize) -> Result<(), BoxError> {
+        let n = u32::try_from(n).map_err(|_| "index too large for the index file format")?;
+        out.extend_from_slice(&n.to_le_bytes());
+        Ok(())
+    }
+
+    let mut keys: Vec<&String> = dictionary_del_mappings.keys().collect();
+    keys.sort_unstable();
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    put_u32(&mut out, max_edit_distance)?;
+    put_u32(&mut out, dictionary.len())?;
+    out.extend_from_slice(&dictionary_fingerprint(dictionary).to_le_bytes());
+    put_u32(&mut out, keys.len())?;
+    for key in keys {
+        put_u32(&mut out, key.len())?;
+        out.extend_from_slice(key.as_bytes());
+        let postings = &dictionary_del_mappings[key];
+        put_u32(&mut out, postings.len())?;
+        for &index in postings {
+            put_u32(&mut out, index)?;
+        }
+    }
+    fs::write(file_path, out)?;
+    Ok(())
+}
+
+/// Reads an index written by `write_index`, checking that it was built for
+/// `dictionary` at `max_edit_distance` and that every posting is in range.
+pub(crate) fn read_index(
+    file_path: &str,
+    dictionary: &[String],
+    max_edit_distance: usize,
+) -> Result<HashMap<String, Vec<usize>>, BoxError> {
+    let bytes = fs::read(file_path)?;
+    let mut reader = Reader {
+        bytes: &bytes,
+        pos: 0,
+    };
+
+    if reader.take(MAGIC.len())? != MAGIC {
+        return Err("not a deletion index file".into());
+    }
+    let file_distance = reader.u32()?;
+    if file_distance != max_edit_distance {
+        return Err(format!(
+            "index was built for max edit distance {}, corrector uses {}",
+            file_distance, max_edit_distance
+        )
+        .into());
+    }
+    let n_words = reader.u32()?;
+    let fingerprint = u64::from_le_bytes(reader.take(8)?.try_into().unwrap());
+    if n_words != dictionary.len() || fingerprint != dictionary_fingerprint(dictionary) {
+        return Err("index was built for a different dictionary".into());
+    }
+
+    // counts come from the file, so reserve no more than the bytes left could
+    // hold: a key takes at least its two lengths, a posting its index
+    let n_keys = reader.u32()?;
+    let mut mappings = HashMap::with_capacity(n_keys.min(reader.remaining() / 8));
+    for _ in 0..n_keys {
+        let key_len = reader.u32()?;
+        let key = std::str::from_utf8(reader.take(key_len)?)?.to_string();
+        let n_postings = reader.u32()?;
+        let mut postings = Vec::with_capacity(n_postings.min(reader.remaining() / 4));
+        for _ in 0..n_postings {
+            let index = reader.u32()?;
+            if index >= n_words {
+                return Err(
+                    format!("index entry {} is out of range for key {:?}", index, key).into(),
+                );
+            }
+            postings.push(index);
+        }
+        mappings.insert(key, postings);
+    }
+    if reader.pos != bytes.len() {
+        return Err("trailing data after deletion index".into());
+    }
+    Ok(mappings)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], BoxError> {
+        let end = self
+            .pos
+            .checked_add(n)
+            .filter(|&end| end <= self.bytes.len());
+        let end = end.ok_or("deletion index file is truncated")?;
+        let slice = &self.bytes[self.pos..end];
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn remaining(&self) -> usize {
+        self.bytes.len() - self.pos
+    }
+
+    fn u32(&mut self) -> Result<usize, BoxError> {
+        Ok(u32::from_le_bytes(self.take(4)?.try_into().unwrap()) as usize)
+    }
+}
@@ -1,2 +1,30 @@
+pub mod dictionary;
+pub mod distance;
+mod index_file;
+#[cfg(feature = "mmap")]
+pub mod mmap;
+pub mod multilang;
+pub mod phonetic;
+pub mod shared;
 pub mod spellcheck;
-pub use spellcheck::{SpellCorrector, SuggestedCorrection, Suggestion};
+pub mod tokenize;
+#[cfg(feature = "fst")]
+pub use dictionary::FstDictionary;
+pub use dictionary::{Dictionary, HashedDictionary, InMemoryDictionary};
+pub use distance::{
+    DiffSpan, ErrorModel, WeightedEditCosts, bounded_edit_distance, channel_log_probability,
+    edit_diff, prewarm_levenshtein_scratch, weighted_edit_distance,
+};
+#[cfg(feature = "mmap")]
+pub use mmap::MmapSpellCorrector;
+pub use multilang::MultiLanguageCorrector;
+pub use phonetic::metaphone;
+pub use shared::SharedSpellCorrector;
+#[cfg(feature = "smallvec")]
+pub use spellcheck::SmallSuggestions;
+pub use spellcheck::{
+    Correction, CorrectorStats, DeletionDepthFn, DictionaryDiff, DistanceFn, EvaluationReport,
+    IndexBuildStats, MatchKind, QueryTrace, Segmentation, SpellCorrector, SpellCorrectorBuilder,
+    SuggestedCorrection, Suggestion, TracedSuggestion, UnknownPolicy, WhitespaceMode,
+    deletion_variants, ocr_confusions,
+};
@@ -0,0 +1,421 @@
+//! A compact, memory-mapped on-disk format for large dictionaries.
+//!
+//! `SpellCorrector::save_compact` writes the dictionary and deletion index in
+//! a flat little-endian layout; `MmapSpellCorrector::open` maps that file and
+//! answers queries by binary search over it, so nothing is parsed or copied
+//! into the heap up front and only the pages a query touches become resident.
+//!
+//! Layout, after an 8-byte magic and a header of `u32` counts:
+//!
+//! ```text
+//! word_offsets     (n_words + 1) x u32   byte ranges into word_bytes
+//! frequencies      n_words x u64
+//! sorted_words     n_words x u32         word indices in byte order
+//! key_offsets      (n_keys + 1) x u32    byte ranges into key_bytes
+//! posting_offsets  (n_keys + 1) x u32    ranges into postings
+//! postings         n_postings x u32      word indices per key
+//! word_bytes       UTF-8 words, concatenated
+//! key_bytes        UTF-8 deletion keys in byte order, concatenated
+//! ```
+use crate::spellcheck::{
+    SuggestedCorrection, Suggestion, bounded_levenshtein, default_suggestion_order,
+    deletion_variants,
+};
+use memmap2::Mmap;
+use std::cmp::Ordering;
+use std::collections::{HashMap, HashSet};
+use std::fs::{self, File};
+
+const MAGIC: &[u8; 8] = b"SPCKMMAP";
+const VERSION: u32 = 1;
+const HEADER_LEN: usize = 8 + 7 * 4;
+
+pub(crate) fn write_compact(
+    dictionary: &[String],
+    frequencies: &[u64],
+    dictionary_del_mappings: &HashMap<String, Vec<usize>>,
+    max_edit_distance: usize,
+    file_path: &str,
+) -> Result<(), Box<dyn std::error::Error>> {
+    fn to_u32(n: usize) -> Result<u32, Box<dyn std::error::Error>> {
+        u32::try_from(n).map_err(|_| "dictionary too large for the compact format".into())
+    }
+
+    let mut keys: Vec<&String> = dictionary_del_mappings.keys().collect();
+    keys.sort_unstable();
+    let mut sorted_words: Vec<usize> = (0..dictionary.len()).collect();
+    sorted_words.sort_by(|&a, &b| dictionary[a].cmp(&dictionary[b]));
+    let n_postings: usize = dictionary_del_mappings.values().map(|v| v.len()).sum();
+    let word_bytes: usize = dictionary.iter().map(|w| w.len()).sum();
+    let key_bytes: usize = keys.iter().map(|k| k.len()).sum();
+
+    let mut out = Vec::new();
+    out.extend_from_slice(MAGIC);
+    for n in [
+        VERSION as usize,
+        max_edit_distance,
+        dictionary.len(),
+        keys.len(),
+        n_postings,
+        word_bytes,
+        key_bytes,
+    ] {
+        out.extend_from_slice(&to_u32(n)?.to_le_bytes());
+    }
+
+    let mut offset = 0;
+    out.extend_from_slice(&0u32.to_le_bytes());
+    for word in dictionary {
+        offset += word.len();
+        out.extend_from_slice(&to_u32(offset)?.to_le_bytes());
+    }
+    for &frequency in frequencies {
+        out.extend_from_slice(&frequency.to_le_bytes());
+    }
+    for &index in &sorted_words {
+        out.extend_from_slice(&to_u32(index)?.to_le_bytes());
+    }
+    let mut offset = 0;
+    out.extend_from_slice(&0u32.to_le_bytes());
+    for key in &keys {
+        offset += key.len();
+        out.extend_from_slice(&to_u32(offset)?.to_le_bytes());
+    }
+    let mut offset = 0;
+    out.extend_from_slice(&0u32.to_le_bytes());
+    for key in &keys {
+        offset += dictionary_del_mappings[*key].len();
+        out.extend_from_slice(&to_u32(offset)?.to_le_bytes());
+    }
+    for key in &keys {
+        for &index in &dictionary_del_mappings[*key] {
+            out.extend_from_slice(&to_u32(index)?.to_le_bytes());
+        }
+    }
+    for word in dictionary {
+        out.extend_from_slice(word.as_bytes());
+    }
+    for key in &keys {
+        out.extend_from_slice(key.as_bytes());
+    }
+
+    fs::write(file_path, out)?;
+    Ok(())
+}
+
+/// A read-only corrector backed by a memory-mapped file written with
+/// `SpellCorrector::save_compact`.
+///
+/// Suggestions are ranked like `SpellCorrector`'s defaults (distance, then
+/// frequency, length and alphabetical order). There is no cache, words cannot
+/// be added, and word metadata is not stored.
+pub struct MmapSpellCorrector {
+    mmap: Mmap,
+    max_edit_distance: usize,
+    n_words: usize,
+    n_keys: usize,
+    word_offsets: usize, // byte positions of each section in `mmap`
+    frequencies: usize,
+    sorted_words: usize,
+    key_offsets: usize,
+    posting_offsets: usize,
+    postings: usize,
+    word_bytes: usize,
+    key_bytes: usize,
+}
+
+impl MmapSpellCorrector {
+    /// Maps a file written by `SpellCorrector::save_compact`. Every offset
+    /// and word index in it is checked here, so a truncated or corrupt file
+    /// fails to open rather than panicking on some later query.
+    pub fn open(file_path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let file = File::open(file_path)?;
+        // Safety: the mapping is read-only; like any mmap it assumes the file
+        // is not truncated or rewritten while mapped.
+        let mmap = unsafe { Mmap::map(&file)? };
+        if mmap.len() < HEADER_LEN || &mmap[..8] != MAGIC {
+            return Err("not a compact spell corrector file".into());
+        }
+        let header = |i: usize| -> usize {
+            let pos = 8 + i * 4;
+            u32::from_le_bytes(mmap[pos..pos + 4].try_into().unwrap()) as usize
+        };
+        if header(0) != VERSION as usize {
+            return Err(format!("unsupported compact format version {}", header(0)).into());
+        }
+        let (max_edit_distance, n_words, n_keys, n_postings) =
+            (header(1), header(2), header(3), header(4));
+        let (word_byte_len, key_byte_len) = (header(5), header(6));
+
+        let word_offsets = HEADER_LEN;
+        let frequencies = word_offsets + (n_words + 1) * 4;
+        let sorted_words = frequencies + n_words * 8;
+        let key_offsets = sorted_words + n_words * 4;
+        let posting_offsets = key_offsets + (n_keys + 1) * 4;
+        let postings = posting_offsets + (n_keys + 1) * 4;
+        let word_bytes = postings + n_postings * 4;
+        let key_bytes = word_bytes + word_byte_len;
+        if mmap.len() != key_bytes + key_byte_len {
+            return Err("compact spell corrector file is truncated or corrupt".into());
+        }
+
+        let corrector = MmapSpellCorrector {
+            mmap,
+            max_edit_distance,
+            n_words,
+            n_keys,
+            word_offsets,
+            frequencies,
+            sorted_words,
+            key_offsets,
+            posting_offsets,
+            postings,
+            word_bytes,
+            key_bytes,
+        };
+        corrector.validate(word_byte_len, key_byte_len, n_postings)?;
+        Ok(corrector)
+    }
+
+    pub fn len(&self) -> usize {
+        self.n_words
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.n_words == 0
+    }
+
+    pub fn max_edit_distance(&self) -> usize {
+        self.max_edit_distance
+    }
+
+    pub fn contains(&self, word: &str) -> bool {
+        self.binary_search(self.n_words, word, |i| {
+            self.word(self.u32_at(self.sorted_words, i))
+        })
+        .is_some()
+    }
+
+    pub fn suggest_single_word_corrections(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+    ) -> SuggestedCorrection {
+        if self.contains(word) {
+            return SuggestedCorrection::NoSuggestions;
+        }
+
+        let mut candidates = HashSet::new();
+        for del_word in deletion_variants(word, self.max_edit_distance, true) {
+            if let Some(key) = self.binary_search(self.n_keys, &del_word, |i| self.key(i)) {
+                let start = self.u32_at(self.posting_offsets, key);
+                let end = self.u32_at(self.posting_offsets, key + 1);
+                candidates.extend((start..end).map(|p| self.u32_at(self.postings, p)));
+            }
+        }
+
+        let mut suggestions: Vec<Suggestion> = candidates
+            .into_iter()
+            .filter_map(|candidate| {
+                let candidate_word = self.word(candidate);
+                let distance = bounded_levenshtein(word, candidate_word, self.max_edit_distance);
+                (distance <= self.max_edit_distance).then(|| Suggestion {
+                    word: candidate_word.to_string(),
+                    distance,
+                    frequency: self.u64_at(self.frequencies, candidate),
+                    metadata: None,
+                })
+            })
+            .collect();
+        let query_len = word.chars().count();
+        suggestions.sort_by(|a, b| default_suggestion_order(query_len, a, b));
+        suggestions.truncate(n_suggestions);
+
+        SuggestedCorrection::Suggestions(suggestions)
+    }
+
+    // Checks every offset and index in the file once, so that queries can
+    // slice the map without bounds failures: offsets must rise from 0 to the
+    // length of the section they point into, words and keys must be UTF-8,
+    // and word indices must be in range.
+    fn validate(
+        &self,
+        word_byte_len: usize,
+        key_byte_len: usize,
+        n_postings: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let corrupt = |what: &str| -> Box<dyn std::error::Error> {
+            format!("compact spell corrector file is corrupt: {what}").into()
+        };
+        let check_offsets = |section: usize, n: usize, total: usize, what: &str| {
+            let mut prev = 0;
+            for i in 0..=n {
+                let offset = self.u32_at(section, i);
+                if (i == 0 && offset != 0) || offset < prev || offset > total {
+                    return Err(corrupt(&format!("{what} offset {i} is out of range")));
+                }
+                prev = offset;
+            }
+            if prev == total {
+                Ok(())
+            } else {
+                Err(corrupt(&format!(
+                    "{what} offsets do not cover their section"
+                )))
+            }
+        };
+        check_offsets(self.word_offsets, self.n_words, word_byte_len, "word")?;
+        check_offsets(self.key_offsets, self.n_keys, key_byte_len, "key")?;
+        check_offsets(self.posting_offsets, self.n_keys, n_postings, "posting")?;
+
+        for (section, n, what) in [
+            (self.sorted_words, self.n_words, "sorted word"),
+            (self.postings, n_postings, "posting"),
+        ] {
+            if let Some(i) = (0..n).find(|&i| self.u32_at(section, i) >= self.n_words) {
+                return Err(corrupt(&format!("{what} {i} names a word out of range")));
+            }
+        }
+        for (bytes, offsets, n, what) in [
+            (self.word_bytes, self.word_offsets, self.n_words, "word"),
+            (self.key_bytes, self.key_offsets, self.n_keys, "key"),
+        ] {
+            for i in 0..n {
+                let start = bytes + self.u32_at(offsets, i);
+                let end = bytes + self.u32_at(offsets, i + 1);
+                if std::str::from_utf8(&self.mmap[start..end]).is_err() {
+                    return Err(corrupt(&format!("{what} {i} is not UTF-8")));
+                }
+            }
+        }
+        Ok(())
+    }
+
+    fn binary_search<'a>(
+        &'a self,
+        len: usize,
+        target: &str,
+        item: impl Fn(usize) -> &'a str,
+    ) -> Option<usize> {
+        let (mut lo, mut hi) = (0, len);
+        while lo < hi {
+            let mid = lo + (hi - lo) / 2;
+            match item(mid).as_bytes().cmp(target.as_bytes()) {
+                Ordering::Less => lo = mid + 1,
+                Ordering::Greater => hi = mid,
+                Ordering::Equal => return Some(mid),
+            }
+        }
+        None
+    }
+
+    fn word(&self, index: usize) -> &str {
+        let start = self.word_bytes + self.u32_at(self.word_offsets, index);
+        let end = self.word_bytes + self.u32_at(self.word_offsets, index + 1);
+        std::str::from_utf8(&self.mmap[start..end]).expect("checked by `open`")
+    }
+
+    fn key(&self, index: usize) -> &str {
+        let start = self.key_bytes + self.u32_at(self.key_offsets, index);
+        let end = self.key_bytes + self.u32_at(self.key_offsets, index + 1);
+        std::str::from_utf8(&self.mmap[start..end]).expect("checked by `open`")
+    }
+
+    fn u32_at(&self, section: usize, index: usize) -> usize {
+        let pos = section + index * 4;
+        u32::from_le_bytes(self.mmap[pos..pos + 4].try_into().unwrap()) as usize
+    }
+
+    fn u64_at(&self, section: usize, index: usize) -> u64 {
+        let pos = section + index * 8;
+        u64::from_le_bytes(self.mmap[pos..pos + 8].try_into().unwrap())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::spellcheck::SpellCorrector;
+
+    #[test]
+    fn test_mmap_round_trip_queries() {
+        let words = vec![
+            ("spelling".to_string(), 10),
+            ("spilling".to_string(), 20),
+            ("selling".to_string(), 5),
+            ("café".to_string(), 1),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 2);
+        let path = std::env::temp_dir().join(format!("spellcheck_mmap_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        corrector.save_compact(path).unwrap();
+
+        let mapped = MmapSpellCorrector::open(path).unwrap();
+        assert_eq!(mapped.len(), 4);
+        assert!(mapped.contains("café"));
+        assert!(!mapped.contains("cafe"));
+        for query in ["speling", "cafe", "seling", "zzzz"] {
+            let expected = corrector.suggest_single_word_corrections(query, 3);
+            let actual = mapped.suggest_single_word_corrections(query, 3);
+            match (expected, actual) {
+                (SuggestedCorrection::Suggestions(a), SuggestedCorrection::Suggestions(b)) => {
+                    let a: Vec<_> = a
+                        .into_iter()
+                        .map(|s| (s.word, s.distance, s.frequency))
+                        .collect();
+                    let b: Vec<_> = b
+                        .into_iter()
+                        .map(|s| (s.word, s.distance, s.frequency))
+                        .collect();
+                    assert_eq!(a, b, "mismatch for {}", query);
+                }
+                _ => panic!("unexpected result for {}", query),
+            }
+        }
+        assert!(matches!(
+            mapped.suggest_single_word_corrections("spelling", 3),
+            SuggestedCorrection::NoSuggestions
+        ));
+
+        fs::write(path, b"garbage").unwrap();
+        assert!(MmapSpellCorrector::open(path).is_err());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_mmap_open_rejects_corruption() {
+        let words = vec![("café".to_string(), 1), ("spelling".to_string(), 2)];
+        let corrector = SpellCorrector::new_with_frequencies(words, 1);
+        let path =
+            std::env::temp_dir().join(format!("spellcheck_mmap_bad_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        corrector.save_compact(path).unwrap();
+        let good = fs::read(path).unwrap();
+        assert!(MmapSpellCorrector::open(path).is_ok());
+
+        let rejects = |bytes: &[u8]| {
+            fs::write(path, bytes).unwrap();
+            MmapSpellCorrector::open(path).is_err()
+        };
+        assert!(rejects(&good[..good.len() - 1]));
+        // the first word's end offset pointing past the word bytes
+        let mut bad = good.clone();
+        bad[HEADER_LEN + 4..HEADER_LEN + 8].copy_from_slice(&u32::MAX.to_le_bytes());
+        assert!(rejects(&bad));
+        // a posting naming word 7 of 2
+        let mut bad = good.clone();
+        let n_keys = u32::from_le_bytes(good[8 + 3 * 4..8 + 4 * 4].try_into().unwrap()) as usize;
+        let postings = HEADER_LEN + 3 * 4 + 2 * 8 + 2 * 4 + 2 * (n_keys + 1) * 4;
+        bad[postings..postings + 4].copy_from_slice(&7u32.to_le_bytes());
+        assert!(rejects(&bad));
+        // invalid UTF-8 inside "café" in the word bytes
+        let mut bad = good.clone();
+        let at = good
+            .windows(5)
+            .position(|w| w == "café".as_bytes())
+            .unwrap();
+        bad[at + 3] = 0xFF;
+        assert!(rejects(&bad));
+        fs::remove_file(path).unwrap();
+    }
+}
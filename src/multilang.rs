@@ -0,0 +1,85 @@
+use crate::spellcheck::{SpellCorrector, SuggestedCorrection};
+use std::collections::HashMap;
+
+/// A set of `SpellCorrector`s keyed by language code (e.g. `"en"`, `"de"`).
+///
+/// The caller always says which language a query belongs to; no language
+/// detection is attempted.
+#[derive(Default)]
+pub struct MultiLanguageCorrector {
+    correctors: HashMap<String, SpellCorrector>,
+}
+
+impl MultiLanguageCorrector {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registers `corrector` for `lang`, returning the corrector it replaced.
+    pub fn register(&mut self, lang: &str, corrector: SpellCorrector) -> Option<SpellCorrector> {
+        self.correctors.insert(lang.to_string(), corrector)
+    }
+
+    pub fn unregister(&mut self, lang: &str) -> Option<SpellCorrector> {
+        self.correctors.remove(lang)
+    }
+
+    pub fn get(&self, lang: &str) -> Option<&SpellCorrector> {
+        self.correctors.get(lang)
+    }
+
+    pub fn get_mut(&mut self, lang: &str) -> Option<&mut SpellCorrector> {
+        self.correctors.get_mut(lang)
+    }
+
+    /// Registered language codes, sorted.
+    pub fn languages(&self) -> Vec<&str> {
+        let mut langs: Vec<&str> = self.correctors.keys().map(|s| s.as_str()).collect();
+        langs.sort_unstable();
+        langs
+    }
+
+    /// Suggests corrections for `word` using the corrector registered for
+    /// `lang`, or `None` if no such language is registered.
+    pub fn suggest(
+        &self,
+        lang: &str,
+        word: &str,
+        n_suggestions: usize,
+    ) -> Option<SuggestedCorrection> {
+        self.correctors
+            .get(lang)
+            .map(|corrector| corrector.suggest_single_word_corrections(word, n_suggestions))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn corrector(words: &[&str]) -> SpellCorrector {
+        SpellCorrector::new(words.iter().map(|s| s.to_string()).collect(), 2)
+    }
+
+    #[test]
+    fn test_dispatches_by_language() {
+        let mut multi = MultiLanguageCorrector::new();
+        multi.register("en", corrector(&["house", "mouse"]));
+        multi.register("de", corrector(&["haus", "maus"]));
+        assert_eq!(multi.languages(), vec!["de", "en"]);
+
+        match multi.suggest("en", "hous", 1) {
+            Some(SuggestedCorrection::Suggestions(list)) => assert_eq!(list[0].word, "house"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        match multi.suggest("de", "hau", 1) {
+            Some(SuggestedCorrection::Suggestions(list)) => assert_eq!(list[0].word, "haus"),
+            other => panic!("unexpected result: {:?}", other),
+        }
+        assert!(matches!(
+            multi.suggest("de", "maus", 1),
+            Some(SuggestedCorrection::NoSuggestions)
+        ));
+        assert!(multi.suggest("fr", "maison", 1).is_none());
+    }
+}
@@ -0,0 +1,170 @@
+//! Phonetic keys for ranking suggestions that sound like the query.
+//!
+//! `metaphone` implements Lawrence Philips' original Metaphone for English.
+//! Only ASCII letters contribute to a key; digits, punctuation and accented
+//! letters are skipped.
+
+/// The Metaphone key of `word`, e.g. "0R" for both "there" and "their".
+///
+/// Keys use the letters B, F, H, J, K, L, M, N, P, R, S, T, W, X (for "sh")
+/// and Y, `0` for "th", and keep a vowel only as the first letter. Empty if
+/// `word` has no ASCII letters.
+pub fn metaphone(word: &str) -> String {
+    let letters: Vec<u8> = word
+        .bytes()
+        .filter(u8::is_ascii_alphabetic)
+        .map(|b| b.to_ascii_uppercase())
+        .collect();
+    let mut key = String::new();
+    let Some(&first) = letters.first() else {
+        return key;
+    };
+
+    let at = |i: usize| letters.get(i).copied().unwrap_or(0);
+    let is_vowel = |b: u8| matches!(b, b'A' | b'E' | b'I' | b'O' | b'U');
+    let is_front_vowel = |b: u8| matches!(b, b'E' | b'I' | b'Y');
+
+    let mut start = 0;
+    match (first, at(1)) {
+        (b'A', b'E') | (b'G', b'N') | (b'K', b'N') | (b'P', b'N') | (b'W', b'R') => start = 1,
+        (b'X', _) => {
+            key.push('S');
+            start = 1;
+        }
+        (b'W', b'H') => {
+            key.push('W');
+            start = 2;
+        }
+        _ => {}
+    }
+
+    let mut i = start;
+    while i < letters.len() {
+        let c = letters[i];
+        let (prev, next, after) = (if i > 0 { at(i - 1) } else { 0 }, at(i + 1), at(i + 2));
+        let is_last = i + 1 == letters.len();
+        // doubled letters sound once, except "cc" as in "accent"
+        if c == prev && c != b'C' && i > start {
+            i += 1;
+            continue;
+        }
+        match c {
+            b'A' | b'E' | b'I' | b'O' | b'U' => {
+                if i == 0 || (i == 1 && start == 1 && first != b'X') {
+                    key.push(c as char);
+                }
+            }
+            b'B' => {
+                if !(prev == b'M' && is_last) {
+                    key.push('B');
+                }
+            }
+            b'C' => {
+                if next == b'I' && after == b'A' {
+                    key.push('X');
+                } else if next == b'H' {
+                    key.push(if prev == b'S' { 'K' } else { 'X' });
+                    i += 1;
+                } else if is_front_vowel(next) {
+                    if prev != b'S' {
+                        key.push('S');
+                    }
+                } else {
+                    key.push('K');
+                }
+            }
+            b'D' => {
+                if next == b'G' && is_front_vowel(after) {
+                    key.push('J');
+                    i += 2;
+                } else {
+                    key.push('T');
+                }
+            }
+            b'G' => {
+                let silent = (next == b'H' && !(i + 2 >= letters.len() || is_vowel(after)))
+                    || (next == b'N' && (i + 2 == letters.len() || &letters[i + 1..] == b"NED"));
+                if !silent {
+                    if is_front_vowel(next) && prev != b'G' {
+                        key.push('J');
+                    } else {
+                        key.push('K');
+                    }
+                }
+            }
+            b'H' => {
+                let after_vowel_only = is_vowel(prev) && !is_vowel(next);
+                if !after_vowel_only && !matches!(prev, b'C' | b'S' | b'P' | b'T' | b'G') {
+                    key.push('H');
+                }
+            }
+            b'K' => {
+                if prev != b'C' {
+                    key.push('K');
+                }
+            }
+            b'P' => {
+                if next == b'H' {
+                    key.push('F');
+                    i += 1;
+                } else {
+                    key.push('P');
+                }
+            }
+            b'Q' => key.push('K'),
+            b'S' => {
+                if next == b'H' {
+                    key.push('X');
+                    i += 1;
+                } else if next == b'I' && matches!(after, b'O' | b'A') {
+                    key.push('X');
+                } else {
+                    key.push('S');
+                }
+            }
+            b'T' => {
+                if next == b'I' && matches!(after, b'O' | b'A') {
+                    key.push('X');
+                } else if next == b'H' {
+                    key.push('0');
+                    i += 1;
+                } else if !(next == b'C' && after == b'H') {
+                    key.push('T');
+                }
+            }
+            b'V' => key.push('F'),
+            b'W' | b'Y' => {
+                if is_vowel(next) {
+                    key.push(c as char);
+                }
+            }
+            b'X' => key.push_str("KS"),
+            b'Z' => key.push('S'),
+            _ => key.push(c as char),
+        }
+        i += 1;
+    }
+    key
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_metaphone_keys() {
+        assert_eq!(metaphone("there"), "0R");
+        assert_eq!(metaphone("their"), "0R");
+        assert_eq!(metaphone("Knight"), "NT");
+        assert_eq!(metaphone("phone"), "FN");
+        assert_eq!(metaphone("school"), "SKL");
+        assert_eq!(metaphone("church"), "XRX");
+        assert_eq!(metaphone("dumb"), "TM");
+        assert_eq!(metaphone("judge"), "JJ");
+        assert_eq!(metaphone("nation"), "NXN");
+        assert_eq!(metaphone("Xavier"), "SFR");
+        assert_eq!(metaphone("apple"), "APL");
+        assert_eq!(metaphone("aerial"), "ERL");
+        assert_eq!(metaphone("42"), "");
+    }
+}
@@ -0,0 +1,102 @@
+use crate::spellcheck::{SpellCorrector, SuggestedCorrection};
+use std::sync::{Arc, Mutex, RwLock};
+
+/// A `SpellCorrector` that can be queried from many threads while words are
+/// being added.
+///
+/// Readers work on immutable snapshots. Updates copy the current corrector,
+/// change the copy and then publish it, so a reader sees either the whole
+/// update or none of it, and never waits for an update to finish. Queries
+/// already running keep using the snapshot they started with; only later
+/// queries see the new words. Each published copy starts with empty caches,
+/// so nothing on the read side has to be invalidated.
+///
+/// Copying is proportional to the dictionary size, so batch several changes
+/// into one `update` where possible.
+pub struct SharedSpellCorrector {
+    current: RwLock<Arc<SpellCorrector>>,
+    writer: Mutex<()>, // serializes updates so none are lost
+}
+
+impl SharedSpellCorrector {
+    pub fn new(corrector: SpellCorrector) -> Self {
+        SharedSpellCorrector {
+            current: RwLock::new(Arc::new(corrector)),
+            writer: Mutex::new(()),
+        }
+    }
+
+    /// The latest published corrector. It never changes, however many updates
+    /// happen while it is held.
+    pub fn snapshot(&self) -> Arc<SpellCorrector> {
+        self.current.read().unwrap().clone()
+    }
+
+    pub fn suggest_single_word_corrections(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+    ) -> SuggestedCorrection {
+        self.snapshot()
+            .suggest_single_word_corrections(word, n_suggestions)
+    }
+
+    /// Applies `f` to a copy of the current corrector and publishes the result.
+    pub fn update<F>(&self, f: F)
+    where
+        F: FnOnce(&mut SpellCorrector),
+    {
+        let _writer = self.writer.lock().unwrap();
+        let mut next = self.snapshot().as_ref().clone();
+        f(&mut next);
+        *self.current.write().unwrap() = Arc::new(next);
+    }
+
+    pub fn add_word_to_dictionary(&self, word: &str) {
+        self.update(|corrector| corrector.add_word_to_dictionary(word));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::atomic::{AtomicBool, Ordering};
+
+    #[test]
+    fn test_readers_see_whole_snapshots() {
+        let added: Vec<String> = (0..20).map(|i| format!("word{:02}x", i)).collect();
+        let shared = SharedSpellCorrector::new(SpellCorrector::new(vec!["base".to_string()], 1));
+        let done = AtomicBool::new(false);
+
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    while !done.load(Ordering::Relaxed) {
+                        let snapshot = shared.snapshot();
+                        // words are added in order, so a snapshot holds a prefix
+                        let known = added.iter().take_while(|w| snapshot.contains(w)).count();
+                        for (i, word) in added.iter().enumerate() {
+                            assert_eq!(snapshot.contains(word), i < known);
+                            // the deletion index agrees with the word list
+                            let found =
+                                match snapshot.suggest_single_word_corrections(&word[..6], 1) {
+                                    SuggestedCorrection::Suggestions(list) => {
+                                        list.iter().any(|s| &s.word == word)
+                                    }
+                                    SuggestedCorrection::NoSuggestions => false,
+                                };
+                            assert_eq!(found, i < known);
+                        }
+                    }
+                });
+            }
+            for word in &added {
+                shared.add_word_to_dictionary(word);
+            }
+            done.store(true, Ordering::Relaxed);
+        });
+
+        let snapshot = shared.snapshot();
+        assert!(added.iter().all(|w| snapshot.contains(w)));
+    }
+}
@@ -1,60 +1,190 @@
+use crate::dictionary::{Dictionary, InMemoryDictionary};
+use crate::distance::{
+    DiffSpan, ErrorModel, WILDCARD_ANY, WILDCARD_OPTIONAL, WeightedEditCosts,
+    bounded_edit_distance, channel_log_probability, edit_diff, edit_position_penalty,
+    longest_common_subsequence, weighted_edit_distance, wildcard_distance,
+};
+use crate::phonetic::metaphone;
+use crate::tokenize::{
+    DEFAULT_CONTRACTIONS, DEFAULT_INVISIBLE_CHARS, DEFAULT_UNITS, is_numeric_token,
+    split_identifier, split_punctuation_with_contractions, strip_diacritics, strip_invisible,
+    tokenize,
+};
 use cachers::{Cache, LFUCache};
 use rayon::prelude::*;
 use serde_json;
+use std::borrow::Cow;
+use std::cmp::{Ordering, Reverse};
 use std::collections::{HashMap, HashSet};
-use std::fs;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::ops::Range;
+use std::sync::atomic::{AtomicU64, Ordering as AtomicOrdering};
+use std::sync::{Arc, OnceLock};
+use std::time::{Duration, Instant};
+use thread_local::ThreadLocal;
 
-fn bounded_levenshtein(a: &str, b: &str, max_dist: usize) -> usize {
-    let (shorter, longer) = if a.len() <= b.len() { (a, b) } else { (b, a) };
-    if longer.len() - shorter.len() > max_dist {
-        return max_dist + 1;
+// Byte-wise; callers that need multi-byte characters to count once can use
+// `bounded_edit_distance` over chars. Distances beyond `max_dist` come back
+// as exactly `max_dist + 1`, so callers may compare against the bound but
+// must not read anything into how far past it a word is.
+pub(crate) fn bounded_levenshtein(a: &str, b: &str, max_dist: usize) -> usize {
+    bounded_edit_distance(a.as_bytes(), b.as_bytes(), max_dist)
+}
+
+// Whether `short` is `long` with some chars deleted, i.e. one of its deletion
+// variants at some depth.
+fn is_subsequence(short: &str, long: &str) -> bool {
+    let mut rest = long.chars();
+    short.chars().all(|c| rest.any(|l| l == c))
+}
+
+// Distance, then frequency (higher first), then length (longer first), then
+// alphabetical. Between two words without frequency data, the one whose
+// length is closest to the query's (`query_len`, in chars) comes before the
+// longer-first rule, which on its own favours long words for no good reason.
+pub(crate) fn default_suggestion_order(
+    query_len: usize,
+    a: &Suggestion,
+    b: &Suggestion,
+) -> Ordering {
+    let length_gap = |s: &Suggestion| s.word.chars().count().abs_diff(query_len);
+    a.distance
+        .cmp(&b.distance)
+        .then_with(|| b.frequency.cmp(&a.frequency))
+        .then_with(|| {
+            if a.frequency == 0 && b.frequency == 0 {
+                length_gap(a).cmp(&length_gap(b))
+            } else {
+                Ordering::Equal
+            }
+        })
+        .then_with(|| b.word.len().cmp(&a.word.len()))
+        .then_with(|| a.word.cmp(&b.word))
+}
+
+// How many of a key's words `index_report` lists.
+const REPORT_SAMPLE_WORDS: usize = 5;
+
+// How many suggestions `verify_roundtrip` compares per query.
+const MAX_ROUNDTRIP_SUGGESTIONS: usize = 5;
+
+// Batches with fewer words than this are not worth handing to rayon.
+const DEFAULT_PARALLEL_THRESHOLD: usize = 16;
+
+// Entries per suggestion cache (per thread with `with_per_thread_cache`).
+const CACHE_CAPACITY: usize = 10000;
+
+// Score `correct_text_beam` deducts per edit, on the same log scale as word
+// and bigram counts: one edit is worth about a 7x difference in frequency.
+const BEAM_EDIT_PENALTY: f64 = 2.0;
+
+// Passes suggestions that were found all at once to a `suggest_progressive`
+// callback, one tier per run of equal distances.
+fn hand_over_tiers<F: FnMut(usize, &[Suggestion])>(suggestions: &[Suggestion], on_tier: &mut F) {
+    for tier in suggestions.chunk_by(|a, b| a.distance == b.distance) {
+        on_tier(tier[0].distance, tier);
     }
+}
 
-    let n = longer.len();
-    let mut prev: Vec<usize> = (0..=n).collect();
-    let mut curr = vec![0; n + 1];
-    let long = longer.as_bytes();
+// How `SpellCorrector::pre_search` settled a query, if it did.
+enum PreSearch {
+    Correct,                   // spelled correctly: no suggestions at all
+    Answered(Vec<Suggestion>), // final suggestions, already ranked and truncated
+    Search,                    // look the query up in the caches and the index
+}
 
-    for (i, &sc) in shorter.as_bytes().iter().enumerate() {
-        let row = i + 1;
-        curr[0] = row;
+// One option for a word in `correct_text_beam`.
+struct BeamChoice {
+    text: Option<String>, // replacement text, `None` to remove the word
+    context: String,      // lowercase word used for bigram lookups
+    corrected: bool,      // capitalize at the start of a sentence
+    score: f64,
+}
 
-        let col_min = if row > max_dist { row - max_dist } else { 1 };
-        let col_max = (row + max_dist).min(n);
+// Upper bound on confusion-set expansions tried per query.
+const MAX_CONFUSION_VARIANTS: usize = 64;
 
-        for j in 1..=n {
-            if j < col_min || j > col_max {
-                curr[j] = max_dist + 1;
-                continue;
-            }
-            let cost = if sc == long[j - 1] { 0 } else { 1 };
-            let ins = curr[j - 1] + 1;
-            let del = prev[j] + 1;
-            let sub = prev[j - 1] + cost;
-            curr[j] = ins.min(del).min(sub);
-        }
-        std::mem::swap(&mut prev, &mut curr);
+/// Common OCR misreadings: digits for letters and merged/split glyphs like
+/// "rn" for "m". Keys are what the OCR engine produced, values what the text
+/// probably said. Use with `SpellCorrector::with_confusion_set`.
+pub fn ocr_confusions() -> HashMap<String, Vec<String>> {
+    [
+        ("0", vec!["o"]),
+        ("1", vec!["l", "i"]),
+        ("5", vec!["s"]),
+        ("8", vec!["b"]),
+        ("rn", vec!["m"]),
+        ("vv", vec!["w"]),
+        ("cl", vec!["d"]),
+    ]
+    .into_iter()
+    .map(|(from, to)| (from.to_string(), to.into_iter().map(String::from).collect()))
+    .collect()
+}
+
+// `word`'s characters in sorted order, shared by all of its anagrams.
+fn anagram_key(word: &str) -> String {
+    let mut chars: Vec<char> = word.chars().collect();
+    chars.sort_unstable();
+    chars.into_iter().collect()
+}
+
+fn capitalize_first(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
     }
-    prev[n]
 }
 
-fn deletion_variants(word: &str, max_del: usize, keep_original: bool) -> HashSet<String> {
+// The non-blank lines of a word list file. Tolerates what editors and
+// exporters leave behind: a UTF-8 byte order mark, `\r\n` and bare `\r`
+// line endings, and surrounding whitespace.
+fn word_list_lines(content: &str) -> impl Iterator<Item = &str> {
+    content
+        .strip_prefix('\u{feff}')
+        .unwrap_or(content)
+        .split(['\n', '\r'])
+        .map(str::trim)
+        .filter(|line| !line.is_empty())
+}
+
+/// Every string reachable from `word` by deleting up to `max_del` chars,
+/// plus `word` itself if `keep_original` is set. These are the keys of the
+/// deletion index, so custom `Dictionary` implementations can use it to build
+/// theirs.
+///
+/// Works level by level: the distinct variants with `k` deletions are
+/// expanded into those with `k + 1`, so each distinct variant is expanded
+/// once. Deleting either of two equal adjacent chars gives the same string,
+/// so only the first of a run is deleted, and a candidate is only allocated
+/// once it is known to be new. For a word of `n` chars that is at most
+/// `C(n, k)` variants of `n - k` chars at level `k`.
+pub fn deletion_variants(word: &str, max_del: usize, keep_original: bool) -> HashSet<String> {
     let mut seen = HashSet::new();
     if keep_original {
         seen.insert(word.to_owned());
     }
-    let mut frontier: HashSet<String> = [word.to_owned()].into();
+    // variants never repeat across levels, since each level is one char
+    // shorter than the last
+    let mut frontier: Vec<String> = vec![word.to_owned()];
+    let mut shorter = String::with_capacity(word.len());
 
     for _ in 0..max_del {
-        let mut next = HashSet::new();
+        let mut next = Vec::new();
         for variant in &frontier {
             let chars: Vec<char> = variant.chars().collect();
             for idx in 0..chars.len() {
-                let mut shorter = String::with_capacity(variant.len());
+                if idx > 0 && chars[idx] == chars[idx - 1] {
+                    continue;
+                }
+                shorter.clear();
                 shorter.extend(chars[..idx].iter());
                 shorter.extend(chars[idx + 1..].iter());
-                if seen.insert(shorter.clone()) {
-                    next.insert(shorter);
+                if !seen.contains(shorter.as_str()) {
+                    seen.insert(shorter.clone());
+                    next.push(shorter.clone());
                 }
             }
         }
@@ -70,69 +200,545 @@ fn deletion_variants(word: &str, max_del: usize, keep_original: bool) -> HashSet
 pub struct Suggestion {
     pub word: String,
     pub distance: usize,
+    pub frequency: u64, // corpus count of `word`, 0 if unknown
+    pub metadata: Option<Arc<HashMap<String, String>>>, // see `set_word_metadata`
+}
+
+impl Suggestion {
+    /// Edit distance as a fraction of the suggested word's length in chars,
+    /// so two edits to a 12-letter word score better than two to a 3-letter
+    /// one.
+    pub fn normalized_distance(&self) -> f64 {
+        self.distance as f64 / self.word.chars().count().max(1) as f64
+    }
 }
 
+/// Suggestions stored inline when there are at most five, see
+/// `SpellCorrector::suggest_single_word_corrections_small`.
+#[cfg(feature = "smallvec")]
+pub type SmallSuggestions = smallvec::SmallVec<[Suggestion; 5]>;
+
 #[derive(Debug, Clone)]
+#[must_use = "the word may be misspelled; check the suggestions"]
 pub enum SuggestedCorrection {
     NoSuggestions,
     Suggestions(Vec<Suggestion>),
 }
 
-pub struct SpellCorrector {
-    dictionary: Vec<String>,
-    lkp_dictionary: HashSet<String>, // for fast lookup
-    dictionary_del_mappings: HashMap<String, Vec<usize>>, // deletion edits -> correct word indices
-    max_edit_distance: usize,        // maximum edit distance to consider
-    cache: LFUCache<String, Vec<Suggestion>>, // cache for suggestions
+impl SuggestedCorrection {
+    /// Whether the query was spelled correctly.
+    pub fn is_correct(&self) -> bool {
+        matches!(self, SuggestedCorrection::NoSuggestions)
+    }
+
+    /// The suggestions, best first; empty for a correct word or one with no
+    /// suggestions.
+    pub fn suggestions(&self) -> &[Suggestion] {
+        match self {
+            SuggestedCorrection::NoSuggestions => &[],
+            SuggestedCorrection::Suggestions(list) => list,
+        }
+    }
+
+    /// The top suggestion, if there is one.
+    pub fn best(&self) -> Option<&Suggestion> {
+        self.suggestions().first()
+    }
+
+    pub fn into_suggestions(self) -> Vec<Suggestion> {
+        match self {
+            SuggestedCorrection::NoSuggestions => Vec::new(),
+            SuggestedCorrection::Suggestions(list) => list,
+        }
+    }
+}
+
+/// How a query matched the dictionary, see
+/// `SpellCorrector::suggest_with_match_kind`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MatchKind {
+    /// The query is a dictionary word as written.
+    Exact,
+    /// The query is a dictionary word only after normalization, e.g.
+    /// "Hello" in case-insensitive mode. A UI may accept it silently or
+    /// offer to fix the case.
+    Normalized,
+    /// The query is an abbreviation registered with `add_expansion`; the
+    /// suggestions are its expansions.
+    Abbreviation,
+    /// The query is not a dictionary word; the suggestions, if any, are
+    /// corrections.
+    Corrected,
+    /// The query is the start of the suggested word, see
+    /// `SpellCorrector::complete_or_correct`. The suggestion's `distance` is
+    /// the number of chars left to type rather than a correction's cost.
+    Completion,
+}
+
+/// What `correct_text` does with a misspelled word that has no suggestions.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub enum UnknownPolicy {
+    /// Keep the word as written.
+    #[default]
+    Leave,
+    /// Replace the word with the given marker, e.g. `"[?]"`. Surrounding
+    /// punctuation is kept.
+    Mark(String),
+    /// Drop the whole token, punctuation included.
+    Remove,
+}
+
+/// How `correct_text` rebuilds the whitespace between tokens.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum WhitespaceMode {
+    /// Join tokens with single spaces, dropping leading/trailing whitespace.
+    #[default]
+    Collapse,
+    /// Keep the original whitespace exactly (newlines, tabs, indentation).
+    Preserve,
+}
+
+/// Statistics about building the deletion index, see
+/// `SpellCorrector::build_with_stats`.
+#[derive(Debug, Clone)]
+pub struct IndexBuildStats {
+    pub words: usize,             // dictionary words indexed
+    pub deletion_keys: usize,     // distinct deletion variants
+    pub entries: usize,           // total (deletion variant, word) pairs
+    pub elapsed: Duration,        // time spent building the index
+    pub max_edit_distance: usize, // depth the index was built for
+}
+
+/// Words in one corrector's dictionary but not another's, see
+/// `SpellCorrector::diff`. Both lists are sorted.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct DictionaryDiff {
+    pub added: Vec<String>,   // in the other dictionary only
+    pub removed: Vec<String>, // in this dictionary only
+}
+
+/// One change `correct_text_with_report` made to its input.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Correction {
+    pub span: Range<usize>,      // byte range of the original word in the input
+    pub original: String,        // the word as written, without surrounding punctuation
+    pub replacement: String,     // what it was replaced with; empty if removed
+    pub distance: Option<usize>, // edit distance; None for `UnknownPolicy` marks and removals
+}
+
+/// One way to split a run-on word, see `SpellCorrector::runon_segmentations`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Segmentation {
+    pub segmented: String, // the query split at one point, e.g. "alot" as "a lot"
+    pub corrected: String, // the same split with a misspelt half corrected
+    pub distance: usize,   // edits to the corrected half, 0 if both are words
+    pub score: f64,        // log frequencies of both halves less an edit penalty
+}
+
+/// A snapshot of a corrector's size, configuration and cache behaviour, see
+/// `SpellCorrector::stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CorrectorStats {
+    pub words: usize,             // dictionary words
+    pub deletion_keys: usize,     // distinct deletion variants
+    pub entries: usize,           // total (deletion variant, word) pairs
+    pub estimated_bytes: usize,   // `SpellCorrector::estimate_index_bytes` for the words
+    pub max_edit_distance: usize, // depth the index is built for
+    pub cache_hits: u64,          // queries answered by the suggestion or negative cache
+    pub cache_misses: u64,        // queries that searched the index
+}
+
+/// A suggestion together with the deletion key that matched it to the query.
+#[derive(Debug, Clone)]
+pub struct TracedSuggestion {
+    pub suggestion: Suggestion,
+    pub matched_key: String,
+}
+
+/// Diagnostics for a single query, see `SpellCorrector::trace`.
+#[derive(Debug, Clone)]
+pub struct QueryTrace {
+    pub query: String,
+    pub in_dictionary: bool,
+    pub candidates_considered: usize, // distinct dictionary words whose distance was computed
+    pub suggestions: Vec<TracedSuggestion>,
+}
+
+/// Top-1/top-k accuracy over labeled `(misspelling, correct)` pairs, see
+/// `SpellCorrector::evaluate`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EvaluationReport {
+    pub total: usize,
+    pub k: usize,
+    pub top1_hits: usize, // correct word ranked first
+    pub topk_hits: usize, // correct word anywhere in the top k
+}
+
+impl EvaluationReport {
+    pub fn top1_accuracy(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.top1_hits as f64 / self.total as f64
+    }
+
+    pub fn topk_accuracy(&self) -> f64 {
+        if self.total == 0 {
+            return 0.0;
+        }
+        self.topk_hits as f64 / self.total as f64
+    }
+}
+
+/// A distance metric used in place of the built-in Levenshtein distance.
+///
+/// Called as `f(query, candidate, max_dist)`; it may return any value above
+/// `max_dist` once the candidate is known to be out of range.
+pub type DistanceFn = dyn Fn(&str, &str, usize) -> usize + Send + Sync;
+
+/// Maps a word's length in chars to the deletion depth it is indexed to, see
+/// `SpellCorrector::with_deletion_depth_policy`.
+pub type DeletionDepthFn = dyn Fn(usize) -> usize + Send + Sync;
+
+// Suggestions keyed by (query, n_suggestions, max edit distance): the list
+// for one `n` is not a prefix of the list for another when the fast path
+// cuts short.
+type SuggestionCache = LFUCache<(String, usize, usize), Vec<Suggestion>>;
+
+pub struct SpellCorrector<D: Dictionary = InMemoryDictionary> {
+    dictionary: D,                                       // words and deletion index
+    max_edit_distance: usize,                            // maximum edit distance to consider
+    cache: SuggestionCache,                              // cache for suggestions
+    thread_caches: Option<ThreadLocal<SuggestionCache>>, // replaces `cache` when set
+    negative_cache: Option<LFUCache<String, ()>>,        // words known to have no suggestions
+    negative_cache_capacity: usize,                      // 0 when the negative cache is off
+    cache_hits: AtomicU64,                               // queries answered by either cache
+    cache_misses: AtomicU64,                             // queries that had to search the index
+    pinned: HashMap<String, OnceLock<Vec<Suggestion>>>,  // pinned query results, filled on use
+
+    word_max_edit_distances: HashMap<String, usize>, // per-word limits below max_edit_distance
+    distance_fn: Option<Arc<DistanceFn>>,            // replaces bounded_levenshtein when set
+    deletion_depth: Option<Arc<DeletionDepthFn>>,    // index depth by word length when set
+    confusion_set: Vec<(String, Vec<String>)>,       // confusable substring -> intended, sorted
+
+    correction_overrides: HashMap<String, String>, // hard-coded fixes, tried before the index
+    expansions: HashMap<String, Vec<String>>,      // abbreviation -> full forms, as added
+    case_insensitive: bool,                        // lowercase queries before lookup
+    invisible_chars: Vec<char>,                    // stripped from queries before lookup
+    fold_diacritics: bool,                         // match words ignoring accents
+    fold_hyphens: bool,                            // and/or ignoring hyphens
+
+    anagram_keys: Option<HashMap<String, Vec<usize>>>, // sorted chars -> word indices
+    folded_keys: Option<HashMap<String, Vec<usize>>>,  // folded form -> words it changes
+
+    error_model: Option<ErrorModel>, // noisy-channel ranking when set
+    normalized_ranking: bool,        // rank by distance relative to word length
+    lcs_tie_break: bool,             // break distance ties by common subsequence length
+    phonetic_tie_break: bool,        // then prefer words sounding like the query
+    position_weighted_ranking: bool, // break distance ties by how late the edits fall
+
+    length_ratio_band: Option<(f64, f64)>, // allowed suggestion/query length ratios
+    max_suggestion_len: Option<usize>,     // longer words (in chars) are never suggested
+    min_frequency: u64,                    // rarer words are never suggested (0 = no threshold)
+    wildcards: bool,                       // treat `*` and `?` in queries as wildcards
+    real_word_mode: bool,                  // suggest neighbours of valid words too
+    exclude_input: bool,                   // real-word mode omits the query itself
+    space_edits: bool,                     // inserting or removing one space counts as one edit
+    pad_suggestions: bool,                 // fill short suggestion lists with farther words
+
+    numeric_units: Option<HashSet<String>>, // correct_text keeps numbers with these units
+    unknown_policy: UnknownPolicy,          // correct_text handling of unfixable words
+    whitespace_mode: WhitespaceMode,        // correct_text handling of whitespace
+    contractions: HashSet<String>,          // kept whole by correct_text, e.g. "'tis"
+    acronym_min_len: usize,                 // all-caps words this long are kept, 0 = off
+    identifier_splitting: bool,             // correct_text fixes camelCase/snake_case subwords
+
+    word_metadata: HashMap<String, Arc<HashMap<String, String>>>, // attached to suggestions
+    bigram_frequencies: HashMap<(String, String), u64>,           // adjacent word pair counts
+    acceptances: HashMap<String, HashMap<String, u64>>,           // accepted suggestions per query
+
+    parallel_threshold: usize, // smaller batches are queried serially
+    min_batch_len: usize,      // smallest rayon chunk in batch queries
+    #[cfg(feature = "logging")]
+    slow_query_candidates: Option<usize>, // warn when a query scans more candidates
 }
 
 impl SpellCorrector {
+    /// Indexes `dictionary` for queries up to `max_edit_distance` edits away.
+    ///
+    /// A `max_edit_distance` of 0 gives an exact-match-only corrector: the
+    /// index holds just the words themselves, so `contains` works as usual
+    /// but a misspelling gets an empty suggestion list (correction overrides,
+    /// accent and hyphen folding and wildcards still apply), and `correct_text`
+    /// leaves every word as it is. See `is_exact_match_only`.
     pub fn new(dictionary: Vec<String>, max_edit_distance: usize) -> Self {
-        let mut dictionary_del_mappings = HashMap::new();
-        let mut lkp_dictionary: HashSet<String> = dictionary.iter().cloned().collect();
-        for (i, word) in dictionary.iter().enumerate() {
-            let deletions = deletion_variants(word, max_edit_distance, true);
-            for del_word in &deletions {
-                dictionary_del_mappings
-                    .entry(del_word.clone())
-                    .or_insert_with(Vec::new)
-                    .push(i);
-            }
-            lkp_dictionary.insert(word.clone());
-        }
-        SpellCorrector {
-            dictionary,
-            lkp_dictionary,
-            dictionary_del_mappings,
+        Self::build_with_stats(dictionary, max_edit_distance).0
+    }
+
+    /// Builds a corrector like `new`, also reporting how large the deletion
+    /// index is and how long it took to build. Useful for timing index
+    /// construction separately from file IO and querying.
+    pub fn build_with_stats(
+        dictionary: Vec<String>,
+        max_edit_distance: usize,
+    ) -> (Self, IndexBuildStats) {
+        let frequencies = vec![0; dictionary.len()];
+        Self::build(dictionary, frequencies, max_edit_distance)
+    }
+
+    /// Builds a corrector from `(word, count)` pairs. Among suggestions at the
+    /// same edit distance, more frequent words rank first.
+    pub fn new_with_frequencies(words: Vec<(String, u64)>, max_edit_distance: usize) -> Self {
+        let (dictionary, frequencies) = words.into_iter().unzip();
+        Self::build(dictionary, frequencies, max_edit_distance).0
+    }
+
+    /// Builds a corrector from a word -> count map. Words are indexed in order
+    /// of descending count, then alphabetically, so dictionary indices are
+    /// the same on every run regardless of the map's iteration order.
+    pub fn from_frequency_map(map: HashMap<String, u64>, max_edit_distance: usize) -> Self {
+        let mut words: Vec<(String, u64)> = map.into_iter().collect();
+        words.sort_unstable_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        Self::new_with_frequencies(words, max_edit_distance)
+    }
+
+    fn build(
+        dictionary: Vec<String>,
+        frequencies: Vec<u64>,
+        max_edit_distance: usize,
+    ) -> (Self, IndexBuildStats) {
+        let start = Instant::now();
+        let dictionary = InMemoryDictionary::new(dictionary, frequencies, max_edit_distance);
+        let stats = IndexBuildStats {
+            words: dictionary.words.len(),
+            deletion_keys: dictionary.dictionary_del_mappings.len(),
+            entries: dictionary
+                .dictionary_del_mappings
+                .values()
+                .map(|v| v.len())
+                .sum(),
+            elapsed: start.elapsed(),
             max_edit_distance,
-            cache: LFUCache::new(10000), // cache size of 10000
+        };
+        (Self::from_dictionary(dictionary, max_edit_distance), stats)
+    }
+
+    /// Builds a corrector with the largest `max_edit_distance`, up to the one
+    /// given, whose estimated index size fits in `budget_bytes` (see
+    /// `estimate_index_bytes`). The distance picked is reported in the stats;
+    /// if even an exact-match index is over budget, distance 0 is used.
+    pub fn new_with_memory_budget(
+        dictionary: Vec<String>,
+        max_edit_distance: usize,
+        budget_bytes: usize,
+    ) -> (Self, IndexBuildStats) {
+        let fitting = (0..=max_edit_distance)
+            .rev()
+            .find(|&distance| Self::estimate_index_bytes(&dictionary, distance) <= budget_bytes)
+            .unwrap_or(0);
+        Self::build_with_stats(dictionary, fitting)
+    }
+
+    /// Rough upper bound on the heap memory, in bytes, of a corrector over
+    /// `dictionary` at `max_edit_distance`. Counts every deletion variant of
+    /// every word as its own entry, so real indexes, where variants are
+    /// shared between words, come in somewhat below it.
+    pub fn estimate_index_bytes(dictionary: &[String], max_edit_distance: usize) -> usize {
+        // String + Vec headers, a posting and hash table overhead per key
+        const ENTRY_OVERHEAD: usize = 64;
+        // two copies (list and lookup set) plus a frequency per word
+        const WORD_OVERHEAD: usize = 2 * 24 + 8;
+
+        dictionary
+            .iter()
+            .map(|word| {
+                let variants =
+                    Self::deletion_variant_count(word.chars().count(), max_edit_distance);
+                let entry = ENTRY_OVERHEAD + word.len();
+                variants
+                    .saturating_mul(entry)
+                    .saturating_add(WORD_OVERHEAD + 2 * word.len())
+            })
+            .fold(0, usize::saturating_add)
+    }
+
+    // The sum of C(n, k) for k in 0..=max_edit_distance, saturating at
+    // `usize::MAX`. Each step multiplies before it divides to stay exact, so
+    // the running terms are kept in u128 and only the total is saturated; a
+    // product clamped before its division would under-report.
+    fn deletion_variant_count(n: usize, max_edit_distance: usize) -> usize {
+        let mut variants: u128 = 1;
+        let mut n_choose_k: u128 = 1;
+        for k in 1..=max_edit_distance.min(n) {
+            match n_choose_k.checked_mul((n - k + 1) as u128) {
+                Some(product) => n_choose_k = product / k as u128,
+                None => return usize::MAX,
+            }
+            variants = variants.saturating_add(n_choose_k);
         }
+        usize::try_from(variants).unwrap_or(usize::MAX)
     }
 
     pub fn from_word_list_file(file_path: &str, max_edit_distance: usize) -> Self {
         let content = fs::read_to_string(file_path).expect("Unable to read dictionary file");
-        let dictionary: Vec<String> = content
-            .lines()
-            .map(|s| s.to_string().to_lowercase())
-            .collect();
+        let dictionary: Vec<String> = word_list_lines(&content).map(str::to_lowercase).collect();
         Self::new(dictionary, max_edit_distance)
     }
 
+    /// Builds one corrector from every `*.txt` word list in `dir_path`, e.g.
+    /// separate lists of names, places and technical terms. Words are
+    /// lowercased like in `from_word_list_file`, files are read in name order
+    /// and a word listed in several files is indexed once.
+    pub fn from_directory(dir_path: &str, max_edit_distance: usize) -> Self {
+        Self::from_directory_with_sources(dir_path, max_edit_distance).0
+    }
+
+    /// Like `from_directory`, also returning, for each word, the names (without
+    /// extension) of the files that list it.
+    pub fn from_directory_with_sources(
+        dir_path: &str,
+        max_edit_distance: usize,
+    ) -> (Self, HashMap<String, Vec<String>>) {
+        let mut paths: Vec<_> = fs::read_dir(dir_path)
+            .expect("Unable to read dictionary directory")
+            .map(|entry| entry.expect("Unable to read dictionary directory").path())
+            .filter(|path| path.is_file() && path.extension().is_some_and(|ext| ext == "txt"))
+            .collect();
+        paths.sort();
+
+        let mut dictionary = Vec::new();
+        let mut sources: HashMap<String, Vec<String>> = HashMap::new();
+        for path in paths {
+            let content = fs::read_to_string(&path).expect("Unable to read dictionary file");
+            let source = path
+                .file_stem()
+                .map(|stem| stem.to_string_lossy().into_owned())
+                .unwrap_or_default();
+            for word in word_list_lines(&content).map(str::to_lowercase) {
+                let files = sources.entry(word.clone()).or_default();
+                if files.is_empty() {
+                    dictionary.push(word);
+                }
+                if !files.contains(&source) {
+                    files.push(source.clone());
+                }
+            }
+        }
+        (Self::new(dictionary, max_edit_distance), sources)
+    }
+
     pub fn save_spell_corrector(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let data = serde_json::json!({
-            "dictionary": self.dictionary,
-            "dictionary_del_mappings": self.dictionary_del_mappings,
+        fs::write(file_path, self.to_json().to_string())?;
+        Ok(())
+    }
+
+    // The JSON document written by `save_spell_corrector`.
+    fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "dictionary": self.dictionary.words,
+            "frequencies": self.dictionary.frequencies,
+            "dictionary_del_mappings": self.dictionary.dictionary_del_mappings,
             "max_edit_distance": self.max_edit_distance,
-        });
-        fs::write(file_path, data.to_string())?;
+            "word_max_edit_distances": self.word_max_edit_distances,
+            "word_metadata": self
+                .word_metadata
+                .iter()
+                .map(|(word, metadata)| (word.clone(), metadata.as_ref().clone()))
+                .collect::<HashMap<_, _>>(),
+        })
+    }
+
+    /// Writes the dictionary and deletion index in the compact binary format
+    /// read by `MmapSpellCorrector::open`.
+    #[cfg(feature = "mmap")]
+    pub fn save_compact(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        crate::mmap::write_compact(
+            &self.dictionary.words,
+            &self.dictionary.frequencies,
+            &self.dictionary.dictionary_del_mappings,
+            self.max_edit_distance,
+            file_path,
+        )
+    }
+
+    /// Writes just the deletion index to a standalone binary file, tagged with
+    /// a fingerprint of the dictionary and the max edit distance it was built
+    /// for.
+    pub fn export_index(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        crate::index_file::write_index(
+            &self.dictionary.words,
+            &self.dictionary.dictionary_del_mappings,
+            self.max_edit_distance,
+            file_path,
+        )
+    }
+
+    /// Replaces the deletion index with one written by `export_index`.
+    ///
+    /// Fails, leaving the corrector untouched, if the file was built for a
+    /// different dictionary or max edit distance.
+    pub fn import_index(&mut self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.dictionary.dictionary_del_mappings = crate::index_file::read_index(
+            file_path,
+            &self.dictionary.words,
+            self.max_edit_distance,
+        )?;
+        self.invalidate_caches();
         Ok(())
     }
 
+    /// Checks that the deletion index fits the dictionary, e.g. after
+    /// `import_index` of a file from elsewhere: every index must name a word,
+    /// and every key must be that word with at most as many deletions as the
+    /// word is indexed to. Returns one message per bad entry, sorted.
+    pub fn validate_index(&self) -> Result<(), Vec<String>> {
+        let words = &self.dictionary.words;
+        let mut problems = Vec::new();
+        for (key, indices) in &self.dictionary.dictionary_del_mappings {
+            for &index in indices {
+                let Some(word) = words.get(index) else {
+                    problems.push(format!(
+                        "deletion {key:?} maps to word {index} of a {}-word dictionary",
+                        words.len()
+                    ));
+                    continue;
+                };
+                let depth = self.index_depth(word);
+                let deletions = word.chars().count().checked_sub(key.chars().count());
+                let within = deletions.is_some_and(|d| d <= depth) && is_subsequence(key, word);
+                if !within {
+                    problems.push(format!(
+                        "deletion {key:?} is not within {depth} deletions of {word:?}"
+                    ));
+                }
+            }
+        }
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            problems.sort();
+            Err(problems)
+        }
+    }
+
     pub fn load_spell_corrector(file_path: &str) -> Self {
         let content = fs::read_to_string(file_path).expect("Unable to read dictionary file");
-        let data: serde_json::Value = serde_json::from_str(&content).expect("Unable to parse JSON");
+        Self::from_json(&content)
+    }
+
+    // Parses a document written by `to_json`, panicking like
+    // `load_spell_corrector` on malformed input.
+    fn from_json(content: &str) -> Self {
+        let data: serde_json::Value = serde_json::from_str(content).expect("Unable to parse JSON");
         let dictionary: Vec<String> =
             serde_json::from_value(data["dictionary"].clone()).expect("Unable to parse dictionary");
+        // files saved before frequency support have no counts
+        let frequencies: Vec<u64> = if data["frequencies"].is_null() {
+            vec![0; dictionary.len()]
+        } else {
+            serde_json::from_value(data["frequencies"].clone())
+                .expect("Unable to parse frequencies")
+        };
         let dictionary_del_mappings: HashMap<String, Vec<usize>> =
             serde_json::from_value(data["dictionary_del_mappings"].clone())
                 .expect("Unable to parse dictionary deletion mappings");
@@ -140,172 +746,2920 @@ impl SpellCorrector {
         let max_edit_distance: usize = serde_json::from_value(data["max_edit_distance"].clone())
             .expect("Unable to parse max edit distance");
 
-        let mut lkp_dictionary = HashSet::new();
-        for word in &dictionary {
-            lkp_dictionary.insert(word.clone());
+        // fail here rather than with an index panic on some later query
+        assert_eq!(
+            frequencies.len(),
+            dictionary.len(),
+            "Corrupt spell corrector file: frequencies do not match the dictionary"
+        );
+        for (del_word, indices) in &dictionary_del_mappings {
+            if let Some(&index) = indices.iter().find(|&&i| i >= dictionary.len()) {
+                panic!(
+                    "Corrupt spell corrector file: deletion {:?} maps to word {} of a {}-word dictionary",
+                    del_word,
+                    index,
+                    dictionary.len()
+                );
+            }
         }
 
-        SpellCorrector {
-            dictionary,
-            lkp_dictionary,
-            dictionary_del_mappings,
-            max_edit_distance,
-            cache: LFUCache::new(10000), // cache size of 10000
+        let dictionary =
+            InMemoryDictionary::from_parts(dictionary, frequencies, dictionary_del_mappings);
+        let mut corrector = Self::from_dictionary(dictionary, max_edit_distance);
+        if !data["word_max_edit_distances"].is_null() {
+            corrector.word_max_edit_distances =
+                serde_json::from_value(data["word_max_edit_distances"].clone())
+                    .expect("Unable to parse per-word max edit distances");
         }
+        if !data["word_metadata"].is_null() {
+            let word_metadata: HashMap<String, HashMap<String, String>> =
+                serde_json::from_value(data["word_metadata"].clone())
+                    .expect("Unable to parse word metadata");
+            corrector.word_metadata = word_metadata
+                .into_iter()
+                .map(|(word, metadata)| (word, Arc::new(metadata)))
+                .collect();
+        }
+        corrector
     }
 
-    pub fn add_word_to_dictionary(&mut self, word: &str) {
-        self.dictionary.push(word.to_string());
-        let deletions = deletion_variants(word, self.max_edit_distance, true);
-        for del_word in &deletions {
-            self.dictionary_del_mappings
-                .entry(del_word.clone())
-                .or_insert_with(Vec::new)
-                .push(self.dictionary.len() - 1);
+    /// Self-check for the `save_spell_corrector` format: serializes the
+    /// corrector in memory, reloads it and compares suggestions for up to
+    /// `sample_size` dictionary words, each with its middle char removed and
+    /// as spelled. Cheap enough to run at startup after loading a saved
+    /// corrector.
+    ///
+    /// Settings that are not saved are copied over to the reloaded corrector,
+    /// so only the saved state is checked. Fails with the first query whose
+    /// suggestions differ.
+    pub fn verify_roundtrip(&self, sample_size: usize) -> Result<(), Box<dyn std::error::Error>> {
+        let loaded = Self::from_json(&self.to_json().to_string());
+        let mut reloaded = self.clone();
+        reloaded.max_edit_distance = loaded.max_edit_distance;
+        reloaded.dictionary = loaded.dictionary;
+        reloaded.word_max_edit_distances = loaded.word_max_edit_distances;
+        reloaded.word_metadata = loaded.word_metadata;
+        reloaded.invalidate_caches();
+
+        let key = |corrector: &Self, query: &str| {
+            let suggestions: Option<Vec<Suggestion>> =
+                corrector.collect_suggestions(query, MAX_ROUNDTRIP_SUGGESTIONS);
+            suggestions.map(|list| {
+                list.into_iter()
+                    .map(|s| (s.word, s.distance, s.frequency, s.metadata))
+                    .collect::<Vec<_>>()
+            })
+        };
+        let words = &self.dictionary.words;
+        let step = (words.len() / sample_size.max(1)).max(1);
+        for word in words.iter().step_by(step).take(sample_size) {
+            let chars: Vec<char> = word.chars().collect();
+            let mut misspelled = chars.clone();
+            if !misspelled.is_empty() {
+                misspelled.remove(chars.len() / 2);
+            }
+            let misspelled: String = misspelled.into_iter().collect();
+            for query in [misspelled.as_str(), word.as_str()] {
+                if key(self, query) != key(&reloaded, query) {
+                    return Err(
+                        format!("suggestions for {:?} differ after reloading", query).into(),
+                    );
+                }
+            }
         }
-        self.lkp_dictionary.insert(word.to_string());
-        self.cache.clear(); // clear the cache when adding a new word
+        Ok(())
     }
 
-    pub fn suggest_single_word_corrections(
-        &self,
-        word: &str,
-        n_suggestions: usize,
-    ) -> SuggestedCorrection {
-        if self.lkp_dictionary.contains(word) {
-            return SuggestedCorrection::NoSuggestions;
-        }
-
-        if let Some(cached_suggestions) = self.cache.get(&word.to_string()) {
-            if cached_suggestions.len() > n_suggestions {
-                return SuggestedCorrection::Suggestions(
-                    cached_suggestions
-                        .iter()
-                        .take(n_suggestions)
-                        .cloned()
-                        .collect(),
-                );
-            }
+    pub fn add_word_to_dictionary(&mut self, word: &str) {
+        self.add_word_to_dictionary_with_frequency(word, 0);
+    }
+
+    /// Adds `word` with a corpus count so it ranks alongside existing words
+    /// instead of below every word with a known frequency. If `word` is
+    /// already in the dictionary, `frequency` is added to its count instead.
+    pub fn add_word_to_dictionary_with_frequency(&mut self, word: &str, frequency: u64) {
+        let word = self.normalize_query(word).into_owned();
+        let index = self.dictionary.len();
+        let key = self.fold_spelling(&word).into_owned();
+        let depth = self.index_depth(&word);
+        self.dictionary.push(word, frequency, depth);
+        if self.dictionary.len() > index
+            && let Some(keys) = &mut self.folded_keys
+            && self.dictionary.words[index] != key
+        {
+            keys.entry(key).or_default().push(index);
+        }
+        if self.dictionary.len() > index
+            && let Some(keys) = &mut self.anagram_keys
+        {
+            keys.entry(anagram_key(&self.dictionary.words[index]))
+                .or_default()
+                .push(index);
         }
+        self.invalidate_caches(); // clear the cache when adding a new word
+    }
 
-        let word_deletions = deletion_variants(word, self.max_edit_distance, false);
-        let mut candidates = HashSet::new();
+    /// A plain-text report of the `top_n` deletion keys shared by the most
+    /// words, for debugging a dictionary: each such "hot" key is scanned by
+    /// every query that produces it, so these buckets explain slow queries
+    /// and crowded suggestion lists. One line per key with its word count,
+    /// the key and up to five of its words, most crowded
+    /// first, after a summary line.
+    #[must_use]
+    pub fn index_report(&self, top_n: usize) -> String {
+        let mappings = &self.dictionary.dictionary_del_mappings;
+        let entries: usize = mappings.values().map(|v| v.len()).sum();
+        let mut keys: Vec<(&String, &Vec<usize>)> = mappings.iter().collect();
+        keys.sort_by(|a, b| b.1.len().cmp(&a.1.len()).then_with(|| a.0.cmp(b.0)));
 
-        for del_word in &word_deletions {
-            if let Some(words) = self.dictionary_del_mappings.get(del_word) {
-                candidates.extend(words.iter().cloned());
+        let mut report = format!(
+            "{} words, {} deletion keys, {} entries (max edit distance {})\n",
+            self.dictionary.words.len(),
+            mappings.len(),
+            entries,
+            self.max_edit_distance
+        );
+        for (key, indices) in keys.into_iter().take(top_n) {
+            let mut words: Vec<&str> = indices
+                .iter()
+                .take(REPORT_SAMPLE_WORDS)
+                .map(|&i| self.dictionary.words[i].as_str())
+                .collect();
+            words.sort_unstable();
+            let more = indices.len().saturating_sub(REPORT_SAMPLE_WORDS);
+            report.push_str(&format!(
+                "{:>8}  {:?}  {}",
+                indices.len(),
+                key,
+                words.join(", ")
+            ));
+            if more > 0 {
+                report.push_str(&format!(" (+{} more)", more));
             }
+            report.push('\n');
         }
+        report
+    }
 
-        let mut suggestions: Vec<Suggestion> = candidates
-            .into_iter()
-            .filter_map(|candidate| {
-                let distance =
-                    bounded_levenshtein(word, &self.dictionary[candidate], self.max_edit_distance);
-                if distance <= self.max_edit_distance {
-                    Some(Suggestion {
-                        word: self.dictionary[candidate].clone(),
-                        distance,
-                    })
-                } else {
-                    None
-                }
-            })
-            .collect();
+    /// Dictionary and index sizes, the configured distance and cache hit
+    /// counts in one snapshot, e.g. for a metrics endpoint. Hits and misses
+    /// count every query since the corrector was built (a clone starts from
+    /// zero); words that are correct, overridden or abbreviations never reach
+    /// the cache and count as neither. The corrector has no prefix length
+    /// setting: the index covers whole words.
+    #[must_use]
+    pub fn stats(&self) -> CorrectorStats {
+        let mappings = &self.dictionary.dictionary_del_mappings;
+        CorrectorStats {
+            words: self.dictionary.words.len(),
+            deletion_keys: mappings.len(),
+            entries: mappings.values().map(|v| v.len()).sum(),
+            estimated_bytes: Self::estimate_index_bytes(
+                &self.dictionary.words,
+                self.max_edit_distance,
+            ),
+            max_edit_distance: self.max_edit_distance,
+            cache_hits: self.cache_hits.load(AtomicOrdering::Relaxed),
+            cache_misses: self.cache_misses.load(AtomicOrdering::Relaxed),
+        }
+    }
 
-        suggestions.sort_by(|a, b| {
-            a.distance
-                .cmp(&b.distance)
-                .then_with(|| b.word.len().cmp(&a.word.len()))
-                .then_with(|| a.word.cmp(&b.word))
-        });
+    /// The words that going from this corrector's dictionary to `other`'s
+    /// would add and remove, e.g. to review a new release of a word list
+    /// before rotating it in. Frequencies and settings are not compared.
+    #[must_use]
+    pub fn diff(&self, other: &SpellCorrector) -> DictionaryDiff {
+        let only_in = |a: &InMemoryDictionary, b: &InMemoryDictionary| {
+            let mut words: Vec<String> = a
+                .lkp_dictionary
+                .difference(&b.lkp_dictionary)
+                .cloned()
+                .collect();
+            words.sort_unstable();
+            words
+        };
+        DictionaryDiff {
+            added: only_in(&other.dictionary, &self.dictionary),
+            removed: only_in(&self.dictionary, &other.dictionary),
+        }
+    }
 
-        suggestions.truncate(n_suggestions);
+    /// Removes every word, leaving an empty dictionary and index to be
+    /// repopulated with `add_word_to_dictionary`, and clears the caches.
+    ///
+    /// Configuration is kept: the max edit distance, cache capacities and
+    /// every `with_*` setting, as well as per-word settings keyed by the word
+    /// itself (correction overrides, per-word distance limits, metadata,
+    /// bigram counts), which apply again if the word is re-added.
+    pub fn reset(&mut self) {
+        self.dictionary = InMemoryDictionary::default();
+        if let Some(keys) = &mut self.folded_keys {
+            keys.clear();
+        }
+        if let Some(keys) = &mut self.anagram_keys {
+            keys.clear();
+        }
+        self.invalidate_caches();
+    }
 
-        self.cache.set(word.to_string(), suggestions.clone());
+    /// Indexes words by their sorted characters so `anagrams_of` is a single
+    /// lookup instead of a scan of the dictionary. Off by default; the index
+    /// is kept up to date as words are added.
+    pub fn with_anagram_index(mut self, enabled: bool) -> Self {
+        self.anagram_keys = enabled.then(|| self.build_anagram_keys());
+        self
+    }
 
-        SuggestedCorrection::Suggestions(suggestions.into_iter().collect())
+    fn build_anagram_keys(&self) -> HashMap<String, Vec<usize>> {
+        let mut keys: HashMap<String, Vec<usize>> = HashMap::new();
+        for (index, word) in self.dictionary.words.iter().enumerate() {
+            keys.entry(anagram_key(word)).or_default().push(index);
+        }
+        keys
     }
 
-    pub fn suggest_word_corrections(
-        &self,
-        words: &Vec<String>,
-        n_suggestions: usize,
-    ) -> Vec<SuggestedCorrection> {
-        words
-            // .into_iter()
-            .par_iter()
-            .map(|word| self.suggest_single_word_corrections(&word, n_suggestions))
+    /// Dictionary words made of exactly the same characters as `word`, e.g.
+    /// "silent" and "enlist" for "listen", in dictionary order. `word` itself
+    /// is left out. Characters are compared as given, so only a
+    /// case-insensitive corrector matches "Listen" to "silent".
+    #[must_use]
+    pub fn anagrams_of(&self, word: &str) -> Vec<&str> {
+        let word = self.normalize_query(word);
+        let key = anagram_key(&word);
+        let words = &self.dictionary.words;
+        let indices: Vec<usize> = match &self.anagram_keys {
+            Some(keys) => keys.get(&key).cloned().unwrap_or_default(),
+            None => (0..words.len())
+                .filter(|&i| words[i].len() == word.len() && anagram_key(&words[i]) == key)
+                .collect(),
+        };
+        indices
+            .into_iter()
+            .map(|i| words[i].as_str())
+            .filter(|&candidate| candidate != word)
             .collect()
     }
-}
-
-#[cfg(test)]
-mod tests {
-    use super::*;
 
-    #[test]
-    fn test_bounded_levenshtein() {
-        assert_eq!(bounded_levenshtein("kitten", "sitting", 3), 3);
-        assert_eq!(bounded_levenshtein("flaw", "lawn", 2), 2);
-        assert_eq!(bounded_levenshtein("intention", "execution", 5), 5);
+    /// Indexes each word only to the deletion depth `policy` gives for its
+    /// length in chars (capped at `max_edit_distance`), and rebuilds the
+    /// index. A word indexed to depth `d` is only suggested for queries
+    /// within `d` edits of it, so the index can skip deep expansion where it
+    /// buys little: very short words, where two edits reach almost anything,
+    /// and long words that are distinctive after one. E.g.
+    /// `|len| if (5..=10).contains(&len) { 2 } else { 1 }` at distance 2 keeps
+    /// full depth for medium words only.
+    ///
+    /// The policy is not saved with the corrector; set it again after
+    /// loading to reshape the index.
+    pub fn with_deletion_depth_policy<F>(mut self, policy: F) -> Self
+    where
+        F: Fn(usize) -> usize + Send + Sync + 'static,
+    {
+        self.deletion_depth = Some(Arc::new(policy));
+        self.rebuild_index();
+        self
     }
 
-    #[test]
-    fn test_deletion_variants() {
-        let variants = deletion_variants("spelling", 2, false);
-        assert!(variants.contains("speling"));
-        assert!(variants.contains("speling"));
-        assert!(!variants.contains("spelling"));
+    /// Makes lookups case-insensitive: queries are lowercased before matching,
+    /// and any uppercase dictionary words are lowercased (re-indexing them).
+    /// Words differing only in case, like "Hello" and "hello", are merged
+    /// into one with their frequencies summed.
+    ///
+    /// By default lookups are case-sensitive: with "hello" in the dictionary,
+    /// "Hello" is a misspelling one edit away. `from_word_list_file` already
+    /// lowercases its words, but `new` stores them as given, and
+    /// `correct_text` always lowercases words before looking them up.
+    pub fn with_case_insensitive(mut self, enabled: bool) -> Self {
+        self.case_insensitive = enabled;
+        if enabled
+            && self
+                .dictionary
+                .words
+                .iter()
+                .any(|w| w.chars().any(char::is_uppercase))
+        {
+            // "Hello" and "hello" become one entry with their counts summed
+            let mut positions: HashMap<String, usize> = HashMap::new();
+            let mut words = Vec::with_capacity(self.dictionary.words.len());
+            let mut frequencies: Vec<u64> = Vec::with_capacity(words.capacity());
+            let old_words = std::mem::take(&mut self.dictionary.words);
+            let old_frequencies = std::mem::take(&mut self.dictionary.frequencies);
+            for (word, frequency) in old_words.into_iter().zip(old_frequencies) {
+                let word = word.to_lowercase();
+                match positions.get(&word) {
+                    Some(&at) => frequencies[at] = frequencies[at].saturating_add(frequency),
+                    None => {
+                        positions.insert(word.clone(), words.len());
+                        words.push(word);
+                        frequencies.push(frequency);
+                    }
+                }
+            }
+            self.dictionary.words = words;
+            self.dictionary.frequencies = frequencies;
+            self.rebuild_index();
+        }
+        self.invalidate_caches();
+        self
     }
 
-    #[test]
-    fn test_suggest_single_word_corrections() {
-        let mut dictionary: Vec<String> = Vec::new();
-        dictionary.push("spelling".to_string());
-        dictionary.push("corrected".to_string());
-
-        let spell_corrector = SpellCorrector::new(dictionary, 2);
-        let suggestions = spell_corrector.suggest_single_word_corrections("speling", 2);
+    fn rebuild_index(&mut self) {
+        #[cfg(feature = "logging")]
+        let start = Instant::now();
+        let depths: Vec<usize> = self
+            .dictionary
+            .words
+            .iter()
+            .map(|word| self.index_depth(word))
+            .collect();
+        self.dictionary.rebuild_index_with(|i| depths[i]);
+        if self.folded_keys.is_some() {
+            self.folded_keys = Some(self.build_folded_keys());
+        }
+        if self.anagram_keys.is_some() {
+            self.anagram_keys = Some(self.build_anagram_keys());
+        }
+        self.invalidate_caches();
+        #[cfg(feature = "logging")]
+        log::debug!(
+            "rebuilt deletion index for {} words in {:?}",
+            self.dictionary.len(),
+            start.elapsed()
+        );
+    }
+}
 
-        match suggestions {
-            SuggestedCorrection::NoSuggestions => panic!("Expected suggestions, but got none."),
-            SuggestedCorrection::Suggestions(suggestions) => {
-                assert_eq!(suggestions.len(), 1);
-                assert_eq!(suggestions[0].word, "spelling");
-            }
+impl<D: Dictionary> SpellCorrector<D> {
+    /// Builds a corrector over any `Dictionary`, e.g. one backed by a
+    /// database. Its deletion index must cover `max_edit_distance`.
+    pub fn from_dictionary(dictionary: D, max_edit_distance: usize) -> Self {
+        SpellCorrector {
+            dictionary,
+            max_edit_distance,
+            cache: LFUCache::new(CACHE_CAPACITY),
+            thread_caches: None,
+            negative_cache: None,
+            negative_cache_capacity: 0,
+            cache_hits: AtomicU64::new(0),
+            cache_misses: AtomicU64::new(0),
+            pinned: HashMap::new(),
+            unknown_policy: UnknownPolicy::default(),
+            whitespace_mode: WhitespaceMode::default(),
+            word_max_edit_distances: HashMap::new(),
+            distance_fn: None,
+            deletion_depth: None,
+            confusion_set: Vec::new(),
+            case_insensitive: false,
+            normalized_ranking: false,
+            error_model: None,
+            lcs_tie_break: false,
+            phonetic_tie_break: false,
+            position_weighted_ranking: false,
+            wildcards: false,
+            contractions: DEFAULT_CONTRACTIONS.iter().map(|c| c.to_string()).collect(),
+            invisible_chars: DEFAULT_INVISIBLE_CHARS.to_vec(),
+            numeric_units: None,
+            acronym_min_len: 0,
+            identifier_splitting: false,
+            correction_overrides: HashMap::new(),
+            expansions: HashMap::new(),
+            acceptances: HashMap::new(),
+            fold_diacritics: false,
+            fold_hyphens: false,
+            folded_keys: None,
+            anagram_keys: None,
+            real_word_mode: false,
+            exclude_input: true,
+            space_edits: false,
+            pad_suggestions: false,
+            length_ratio_band: None,
+            max_suggestion_len: None,
+            min_frequency: 0,
+            word_metadata: HashMap::new(),
+            min_batch_len: 1,
+            parallel_threshold: DEFAULT_PARALLEL_THRESHOLD,
+            bigram_frequencies: HashMap::new(),
+            #[cfg(feature = "logging")]
+            slow_query_candidates: None,
         }
     }
 
-    #[test]
-    fn test_bounded_levenshtein_identical() {
-        assert_eq!(bounded_levenshtein("same", "same", 0), 0);
+    /// Enables or disables caching of queries that produced no suggestions.
+    ///
+    /// Repeated unfixable tokens (garbage, IDs, foreign words) then skip the
+    /// candidate scan entirely. Off by default; when enabled it holds as many
+    /// words as the suggestion cache, see `with_negative_cache_capacity`.
+    pub fn with_negative_cache(self, enabled: bool) -> Self {
+        let capacity = if enabled { CACHE_CAPACITY } else { 0 };
+        self.with_negative_cache_capacity(capacity)
     }
 
-    #[test]
-    fn test_bounded_levenshtein_cutoff() {
-        // real distance = 3, bound = 2  ⇒  function must bail out (> bound)
-        assert!(bounded_levenshtein("kitten", "sitting", 2) > 2);
+    /// Enables the negative cache with room for `capacity` words, or disables
+    /// it if `capacity` is 0.
+    ///
+    /// When full, the word with the fewest hits is evicted, so a flood of
+    /// one-off garbage tokens cannot grow memory without bound or push out
+    /// the unfixable tokens that keep recurring.
+    pub fn with_negative_cache_capacity(mut self, capacity: usize) -> Self {
+        self.negative_cache = (capacity > 0).then(|| LFUCache::new(capacity));
+        self.negative_cache_capacity = capacity;
+        self
     }
 
-    #[test]
-    fn test_deletion_variants_zero() {
-        // With max_del = 0 we expect *no* variants
-        assert!(deletion_variants("abc", 0, false).is_empty());
+    /// Gives each thread its own suggestion cache instead of one shared by
+    /// all of them.
+    ///
+    /// The shared cache is a contention point when the batch methods query
+    /// from many rayon workers at once; per-thread caches never contend, but
+    /// a word cached by one thread is a miss on the others, and each thread's
+    /// cache can grow to the full capacity. Worth it for large batches with
+    /// many worker threads; `benches/bench_cache_mode.rs` compares the two.
+    pub fn with_per_thread_cache(mut self, enabled: bool) -> Self {
+        self.thread_caches = enabled.then(ThreadLocal::new);
+        self
     }
 
-    #[test]
-    fn test_deletion_variants_exhaustive() {
-        let v = deletion_variants("abc", 2, false);
-        // All unique strings reachable by 1 or 2 deletions
-        for s in ["ab", "ac", "bc", "a", "b", "c"] {
-            assert!(v.contains(s), "missing variant {}", s);
-        }
-        // Original word must be absent
-        assert!(!v.contains("abc"));
+    /// Sets how `correct_text` treats misspelled words with no suggestions.
+    pub fn with_unknown_policy(mut self, policy: UnknownPolicy) -> Self {
+        self.unknown_policy = policy;
+        self
     }
 
-    #[test]
+    /// Sets whether `correct_text` keeps the original whitespace.
+    pub fn with_whitespace_mode(mut self, mode: WhitespaceMode) -> Self {
+        self.whitespace_mode = mode;
+        self
+    }
+
+    /// Limits how far from a query `word` may be for it to be suggested,
+    /// below the corrector-wide `max_edit_distance`. Useful for short words
+    /// that are too easy to reach, e.g. an override of 0 for `"to"` means it
+    /// is never suggested as a correction.
+    pub fn set_word_max_edit_distance(&mut self, word: &str, max_edit_distance: usize) {
+        self.word_max_edit_distances
+            .insert(word.to_string(), max_edit_distance);
+        self.invalidate_caches();
+    }
+
+    /// Hard-codes `to` as the correction for `from`, e.g. "teh" -> "the".
+    /// Queries for `from` then return `to` as their only suggestion, whatever
+    /// the index holds, even if `from` is itself a dictionary word. `to`
+    /// need not be in the dictionary.
+    pub fn add_correction_override(&mut self, from: &str, to: &str) {
+        self.correction_overrides
+            .insert(self.normalize_query(from).into_owned(), to.to_string());
+    }
+
+    /// Registers `full` as an expansion of the abbreviation `abbr`, e.g.
+    /// "govt" -> "government". Queries for `abbr` then return its expansions,
+    /// in the order they were added, instead of edit-distance suggestions,
+    /// however far they are; `suggest_with_match_kind` reports them as
+    /// `MatchKind::Abbreviation`. Unlike a correction override an
+    /// abbreviation can have several expansions, e.g. "st" -> "street" and
+    /// "saint". A correction override for `abbr` takes precedence.
+    pub fn add_expansion(&mut self, abbr: &str, full: &str) {
+        let fulls = self
+            .expansions
+            .entry(self.normalize_query(abbr).into_owned())
+            .or_default();
+        if !fulls.iter().any(|f| f == full) {
+            fulls.push(full.to_string());
+        }
+        self.invalidate_caches();
+    }
+
+    /// Records that a user shown suggestions for `original` picked `chosen`,
+    /// e.g. from an interactive prompt. Later queries for `original` rank
+    /// its accepted suggestions first, most often accepted first, ahead of
+    /// closer words; `chosen` still has to be within range to be suggested.
+    pub fn record_acceptance(&mut self, original: &str, chosen: &str) {
+        let original = self.normalize_query(original).into_owned();
+        *self
+            .acceptances
+            .entry(original)
+            .or_default()
+            .entry(chosen.to_string())
+            .or_default() += 1;
+        self.invalidate_caches();
+    }
+
+    /// Attaches arbitrary key/value metadata to `word`, e.g. its part of
+    /// speech or a canonical URL, returned on every suggestion of that word.
+    /// Replaces any metadata the word already had.
+    pub fn set_word_metadata(&mut self, word: &str, metadata: HashMap<String, String>) {
+        self.word_metadata
+            .insert(word.to_string(), Arc::new(metadata));
+        self.invalidate_caches();
+    }
+
+    /// The metadata attached to `word` with `set_word_metadata`, if any.
+    pub fn word_metadata(&self, word: &str) -> Option<&HashMap<String, String>> {
+        self.word_metadata
+            .get(word)
+            .map(|metadata| metadata.as_ref())
+    }
+
+    /// Sets several per-word overrides at once, see
+    /// `set_word_max_edit_distance`.
+    pub fn with_word_max_edit_distances(mut self, overrides: HashMap<String, usize>) -> Self {
+        self.word_max_edit_distances.extend(overrides);
+        self.invalidate_caches();
+        self
+    }
+
+    /// Never suggests words longer than `max_len` chars, however close they
+    /// are; they are skipped before their distance is computed.
+    ///
+    /// Independently of this cap, a candidate more than `max_edit_distance`
+    /// chars longer than the query is never verified, since no edit-count
+    /// metric can put it within range.
+    pub fn with_max_suggestion_len(mut self, max_len: usize) -> Self {
+        self.max_suggestion_len = Some(max_len);
+        self.invalidate_caches();
+        self
+    }
+
+    /// Never suggests words with a frequency below `min_frequency`, e.g. to
+    /// keep one-off typos in a crowd-sourced word list from being offered as
+    /// corrections. They still count as correctly spelled. Words without a
+    /// known frequency count as 0, so with a threshold set only words given
+    /// counts are suggested. Correction overrides and abbreviation
+    /// expansions are exempt. 0, the default, turns the filter off.
+    pub fn with_min_frequency(mut self, min_frequency: u64) -> Self {
+        self.min_frequency = min_frequency;
+        self.invalidate_caches();
+        self
+    }
+
+    /// Uses `distance_fn` instead of Levenshtein distance when verifying
+    /// candidates, e.g. a metric weighted by an OCR confusion matrix.
+    ///
+    /// Candidates are still generated from the deletion index, so the metric
+    /// can only re-score and filter words that share a deletion variant with
+    /// the query.
+    pub fn with_distance_fn<F>(mut self, distance_fn: F) -> Self
+    where
+        F: Fn(&str, &str, usize) -> usize + Send + Sync + 'static,
+    {
+        self.distance_fn = Some(Arc::new(distance_fn));
+        self.invalidate_caches();
+        self
+    }
+
+    /// Verifies candidates with `weighted_edit_distance` under `costs`, e.g.
+    /// `WeightedEditCosts::keyboard()` to favour transpositions. Weighted
+    /// distances are compared against `max_edit_distance` as usual.
+    pub fn with_weighted_edit_costs(self, costs: WeightedEditCosts) -> Self {
+        self.with_distance_fn(move |query, candidate, max_dist| {
+            weighted_edit_distance(query, candidate, &costs, max_dist)
+        })
+    }
+
+    /// Expands each query with the given character confusions before looking
+    /// up candidates, e.g. `ocr_confusions()` so that "0cr" finds "ocr".
+    ///
+    /// Each key is replaced by each of its values, wherever it occurs and in
+    /// combination, up to a fixed number of spellings per query. Substituting a
+    /// confusion is free: a suggestion's distance is measured from whichever
+    /// expanded spelling is closest to it.
+    pub fn with_confusion_set(mut self, confusions: HashMap<String, Vec<String>>) -> Self {
+        // sorted so the capped expansion is the same on every run
+        self.confusion_set = confusions
+            .into_iter()
+            .filter(|(from, _)| !from.is_empty())
+            .collect();
+        self.confusion_set.sort();
+        self.invalidate_caches();
+        self
+    }
+
+    fn distance(&self, query: &str, candidate: &str) -> usize {
+        self.distance_within(query, candidate, self.max_edit_distance)
+    }
+
+    fn distance_within(&self, query: &str, candidate: &str, max_dist: usize) -> usize {
+        match &self.distance_fn {
+            Some(distance_fn) => distance_fn(query, candidate, max_dist),
+            None => bounded_levenshtein(query, candidate, max_dist),
+        }
+    }
+
+    /// Replaces the invisible characters stripped from queries and added
+    /// words before lookup, by default `DEFAULT_INVISIBLE_CHARS`, so that
+    /// "hy\u{00AD}phen" matches "hyphen". Pass an empty list to look words up
+    /// exactly as given, e.g. for scripts where the zero-width joiners are
+    /// meaningful.
+    pub fn with_invisible_chars(mut self, chars: &[char]) -> Self {
+        self.invisible_chars = chars.to_vec();
+        self.invalidate_caches();
+        self
+    }
+
+    /// Ranks suggestions by `Suggestion::normalized_distance` instead of raw
+    /// edit distance, falling back to the usual order on ties. Off by
+    /// default; it mainly helps when short and long words compete for the
+    /// same query.
+    pub fn with_normalized_ranking(mut self, enabled: bool) -> Self {
+        self.normalized_ranking = enabled;
+        self.invalidate_caches();
+        self
+    }
+
+    /// Ranks suggestions by an approximate noisy-channel model instead of
+    /// distance first: the most likely intended word maximizes
+    /// `P(word) * P(query | word)`, where `P(word)` grows with the word's
+    /// frequency and `P(query | word)` is the chance of `model`'s typing
+    /// errors turning it into the query (see `channel_log_probability`). A
+    /// very common word two likely errors away can then beat a rare word one
+    /// unlikely error away. Ties keep the usual order.
+    ///
+    /// `P(word)` is the add-one smoothed frequency, so words without counts
+    /// compete on the error model alone. Candidates are still limited to
+    /// `max_edit_distance`; this only reorders them.
+    pub fn with_noisy_channel(mut self, model: ErrorModel) -> Self {
+        self.error_model = Some(model);
+        self.invalidate_caches();
+        self
+    }
+
+    /// Among suggestions at the same distance, ranks first those sharing the
+    /// longest common subsequence of characters with the query, ahead of
+    /// frequency and length. Off by default, since it costs a quadratic
+    /// comparison per tie.
+    pub fn with_lcs_tie_break(mut self, enabled: bool) -> Self {
+        self.lcs_tie_break = enabled;
+        self.invalidate_caches();
+        self
+    }
+
+    /// Among suggestions at the same distance, ranks first those whose edits
+    /// fall later in the query, ahead of frequency and length: a changed
+    /// first letter is more disruptive than a changed last one, so for "mat"
+    /// "map" ranks ahead of "bat". Off by default, since it reconstructs the
+    /// edits of every tied pair.
+    pub fn with_position_weighted_ranking(mut self, enabled: bool) -> Self {
+        self.position_weighted_ranking = enabled;
+        self.invalidate_caches();
+        self
+    }
+
+    /// Among suggestions with the same distance and frequency, ranks first
+    /// those with the same `metaphone` key as the query, so "thier" prefers
+    /// "their" to an equally close word that sounds different. Off by
+    /// default.
+    pub fn with_phonetic_tie_break(mut self, enabled: bool) -> Self {
+        self.phonetic_tie_break = enabled;
+        self.invalidate_caches();
+        self
+    }
+
+    /// Lets queries contain wildcards: `*` matches any one character and `?`
+    /// any one character or none, so "sp*lling" finds "spelling" and
+    /// "spilling" at distance 0. Other characters still allow up to
+    /// `max_edit_distance` edits.
+    ///
+    /// Wildcard queries cannot use the deletion index and compare every word
+    /// of a plausible length instead, so they are much slower than ordinary
+    /// ones. Off by default, when `*` and `?` are ordinary characters.
+    pub fn with_wildcards(mut self, enabled: bool) -> Self {
+        self.wildcards = enabled;
+        self.invalidate_caches();
+        self
+    }
+
+    /// Makes `suggest_single_word_corrections` return nearby alternatives for
+    /// words that are in the dictionary too, for catching real-word errors
+    /// like "form" typed for "from". The query itself is not among the
+    /// suggestions unless `with_exclude_input(false)` is set.
+    ///
+    /// Off by default, when a dictionary word gets `NoSuggestions`.
+    /// `correct_text` leaves dictionary words alone in either mode.
+    pub fn with_real_word_mode(mut self, enabled: bool) -> Self {
+        self.real_word_mode = enabled;
+        self.invalidate_caches();
+        self
+    }
+
+    /// Whether real-word mode filters the query out of its own suggestions,
+    /// so they hold only its neighbours. On by default; turn it off to get
+    /// the query back first, at distance 0, ahead of its neighbours.
+    pub fn with_exclude_input(mut self, enabled: bool) -> Self {
+        self.exclude_input = enabled;
+        self.invalidate_caches();
+        self
+    }
+
+    /// Counts inserting or removing a single space as one edit, so "alot"
+    /// gets "a lot" and "togeth er" gets "together" at distance 1, alongside
+    /// the usual suggestions. A split needs both halves in the dictionary,
+    /// and a join is only offered where one of the joined words is not.
+    /// Split suggestions carry the smaller of their halves' frequencies.
+    pub fn with_space_edits(mut self, enabled: bool) -> Self {
+        self.space_edits = enabled;
+        self.invalidate_caches();
+        self
+    }
+
+    /// When fewer than `n_suggestions` words are within the edit distance,
+    /// fills the list up with the nearest words beyond it, for UIs that show
+    /// a fixed number of choices. The extra words come last and can be told
+    /// apart by a `distance` above the limit. Lists from every source are
+    /// padded, pinned and phrase queries included, and the extra words obey
+    /// `with_min_frequency`, `with_max_suggestion_len` and
+    /// `with_length_ratio_guard`. Finding them scans the whole dictionary, so
+    /// padded queries are much slower than the rest. Off by default, when the
+    /// list is simply shorter.
+    pub fn with_suggestion_padding(mut self, enabled: bool) -> Self {
+        self.pad_suggestions = enabled;
+        self.invalidate_caches();
+        self
+    }
+
+    /// Sets the fewest words rayon hands to one thread at a time in batch
+    /// queries (`suggest_word_corrections` and friends). The default of 1
+    /// lets rayon split freely; larger values cut scheduling overhead on big
+    /// batches of cheap words, smaller ones spread a few expensive words
+    /// across threads. Values below 1 are treated as 1.
+    pub fn with_min_batch_len(mut self, min_len: usize) -> Self {
+        self.min_batch_len = min_len.max(1);
+        self
+    }
+
+    /// Sets the batch size below which `suggest_word_corrections` and
+    /// `suggest_word_corrections_small` query on the calling thread instead
+    /// of through rayon, whose overhead outweighs the gain for a handful of
+    /// words. Defaults to 16; 0 always parallelizes.
+    pub fn with_parallel_threshold(mut self, threshold: usize) -> Self {
+        self.parallel_threshold = threshold;
+        self
+    }
+
+    /// Matches queries against dictionary words ignoring accents, so "cafe"
+    /// finds "café" and "naïve" finds "naive" at distance 0, returning the
+    /// dictionary's spelling. A word that differs from some dictionary word
+    /// only in its accents gets those words as its only suggestions; other
+    /// misspellings go through the index as usual. Off by default.
+    ///
+    /// Accents are removed with `tokenize::strip_diacritics`.
+    pub fn with_diacritic_insensitive(mut self, enabled: bool) -> Self {
+        self.fold_diacritics = enabled;
+        self.refresh_folded_keys();
+        self
+    }
+
+    /// Matches queries against dictionary words ignoring hyphens, like
+    /// `with_diacritic_insensitive` does accents: with "email" in the
+    /// dictionary "e-mail" gets "email" as its only suggestion, at distance
+    /// 0, and with "e-mail" in it "email" gets "e-mail". Off by default.
+    /// Both can be enabled together.
+    pub fn with_hyphen_insensitive(mut self, enabled: bool) -> Self {
+        self.fold_hyphens = enabled;
+        self.refresh_folded_keys();
+        self
+    }
+
+    fn refresh_folded_keys(&mut self) {
+        self.folded_keys = None;
+        if self.fold_diacritics || self.fold_hyphens {
+            self.folded_keys = Some(self.build_folded_keys());
+        }
+        self.invalidate_caches();
+    }
+
+    // `word` with accents and/or hyphens removed, as enabled; borrowed when
+    // that changes nothing.
+    fn fold_spelling<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        let mut folded = Cow::Borrowed(word);
+        if self.fold_diacritics
+            && let Cow::Owned(stripped) = strip_diacritics(word)
+        {
+            folded = Cow::Owned(stripped);
+        }
+        if self.fold_hyphens && folded.contains('-') {
+            folded = Cow::Owned(folded.replace('-', ""));
+        }
+        folded
+    }
+
+    // Only words that folding changes are keyed; the others are found by
+    // looking up the folded query directly.
+    fn build_folded_keys(&self) -> HashMap<String, Vec<usize>> {
+        let mut keys: HashMap<String, Vec<usize>> = HashMap::new();
+        for index in 0..self.dictionary.len() {
+            let word = self.word_at(index);
+            if let Cow::Owned(key) = self.fold_spelling(&word) {
+                keys.entry(key).or_default().push(index);
+            }
+        }
+        keys
+    }
+
+    // Dictionary words equal to `word` once both are folded.
+    fn folded_matches(&self, word: &str) -> Option<Vec<Suggestion>> {
+        let keys = self.folded_keys.as_ref()?;
+        let key = self.fold_spelling(word);
+        let changed = keys.get(key.as_ref()).into_iter().flatten();
+        let mut suggestions: Vec<Suggestion> = changed
+            .map(|&index| self.word_at(index).into_owned())
+            .chain((key != word && self.dictionary.contains(&key)).then(|| key.to_string()))
+            .filter(|candidate| candidate != word)
+            .map(|candidate| Suggestion {
+                distance: 0,
+                frequency: self.frequency_of(&candidate),
+                metadata: self.word_metadata.get(&candidate).cloned(),
+                word: candidate,
+            })
+            .filter(|s| s.frequency >= self.min_frequency)
+            .collect();
+        if suggestions.is_empty() {
+            return None;
+        }
+        self.rank_suggestions(word, &mut suggestions);
+        Some(suggestions)
+    }
+
+    /// Logs a warning for every query whose deletion variants match more
+    /// than `candidates` dictionary words, which is where slow queries spend
+    /// their time. Off by default.
+    #[cfg(feature = "logging")]
+    pub fn with_slow_query_threshold(mut self, candidates: usize) -> Self {
+        self.slow_query_candidates = Some(candidates);
+        self
+    }
+
+    /// Rejects corrections whose length, relative to the misspelled word (in
+    /// chars), falls outside `min_ratio..=max_ratio`, leaving the word as it
+    /// is. Guards against corrections that are technically close but almost
+    /// certainly wrong, like "a" -> "apple" with a large edit distance.
+    ///
+    /// Applies to `correct_text` and `best_correction`; the suggestion lists
+    /// are unaffected, apart from the words `with_suggestion_padding` fills
+    /// them up with. Off by default.
+    pub fn with_length_ratio_guard(mut self, min_ratio: f64, max_ratio: f64) -> Self {
+        self.length_ratio_band = Some((min_ratio, max_ratio));
+        self.invalidate_caches();
+        self
+    }
+
+    fn passes_length_ratio_guard(&self, word: &str, correction: &str) -> bool {
+        match self.length_ratio_band {
+            Some((min_ratio, max_ratio)) => {
+                let ratio = correction.chars().count() as f64 / word.chars().count().max(1) as f64;
+                (min_ratio..=max_ratio).contains(&ratio)
+            }
+            None => true,
+        }
+    }
+
+    /// The largest edit distance suggestions are searched for.
+    pub fn max_edit_distance(&self) -> usize {
+        self.max_edit_distance
+    }
+
+    /// Whether the corrector was built with `max_edit_distance` 0, and so
+    /// can only tell correct words from misspellings, not fix them.
+    pub fn is_exact_match_only(&self) -> bool {
+        self.max_edit_distance == 0
+    }
+
+    /// Whether `word` is in the dictionary, after the corrector's query
+    /// normalization (e.g. lowercasing in case-insensitive mode).
+    pub fn contains(&self, word: &str) -> bool {
+        self.dictionary
+            .contains(self.normalize_query(word).as_ref())
+    }
+
+    // The form of `word` that is actually looked up.
+    fn normalize_query<'a>(&self, word: &'a str) -> Cow<'a, str> {
+        let word = strip_invisible(word, &self.invisible_chars);
+        if self.case_insensitive && word.chars().any(char::is_uppercase) {
+            Cow::Owned(word.to_lowercase())
+        } else {
+            word
+        }
+    }
+
+    fn invalidate_caches(&mut self) {
+        self.cache.clear();
+        if let Some(thread_caches) = &mut self.thread_caches {
+            thread_caches.clear();
+        }
+        if let Some(negative_cache) = &mut self.negative_cache {
+            negative_cache.clear();
+        }
+        // pins are kept but go stale, so a run of changes costs nothing until
+        // the next lookup of each pinned query
+        for pinned in self.pinned.values_mut() {
+            *pinned = OnceLock::new();
+        }
+    }
+
+    /// Precomputes suggestions for `words`, e.g. a service's most frequent
+    /// misspellings, and keeps them outside the LFU cache so no amount of
+    /// other traffic evicts them. Pinned queries are answered from the stored
+    /// list for any `n_suggestions`. Whenever the caches would be cleared,
+    /// e.g. when a word is added or a ranking setting changes, the list goes
+    /// stale and is recomputed on the query's next lookup. Pins accumulate
+    /// over calls.
+    pub fn pin_queries(&mut self, words: &[String]) {
+        for word in words {
+            let word = self.normalize_query(word).into_owned();
+            let suggestions = self.search_suggestions(&word, usize::MAX, self.max_edit_distance);
+            self.pinned.insert(word, OnceLock::from(suggestions));
+        }
+    }
+
+    // The calling thread's cache in per-thread mode, otherwise the shared one.
+    fn suggestion_cache(&self) -> &SuggestionCache {
+        match &self.thread_caches {
+            Some(thread_caches) => thread_caches.get_or(|| LFUCache::new(CACHE_CAPACITY)),
+            None => &self.cache,
+        }
+    }
+
+    fn max_distance_for(&self, index: usize) -> usize {
+        if self.word_max_edit_distances.is_empty() && self.deletion_depth.is_none() {
+            return self.max_edit_distance;
+        }
+        let word = self.dictionary.get(index).unwrap_or_default();
+        match self.word_max_edit_distances.get(word.as_ref()) {
+            Some(&limit) => limit.min(self.index_depth(&word)),
+            None => self.index_depth(&word),
+        }
+    }
+
+    // How deep `word` is indexed: `max_edit_distance` unless a deletion depth
+    // policy says less. Matches beyond it cannot be found reliably, so they
+    // are not suggested at all.
+    fn index_depth(&self, word: &str) -> usize {
+        match &self.deletion_depth {
+            Some(policy) => policy(word.chars().count()).min(self.max_edit_distance),
+            None => self.max_edit_distance,
+        }
+    }
+
+    /// Suggests up to `n_suggestions` corrections for `word`, best first, or
+    /// `NoSuggestions` if it is spelled correctly.
+    ///
+    /// A `word` containing whitespace, e.g. "new yrk", is treated as a phrase
+    /// unless the dictionary lists it as is: each word is corrected on its
+    /// own, giving a suggestion such as "new york" at the summed distance, or
+    /// `NoSuggestions` if every word is correct. Multi-word dictionary entries
+    /// near the whole phrase, e.g. "san francisco" for "san fransisco", are
+    /// suggested alongside it.
+    pub fn suggest_single_word_corrections(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+    ) -> SuggestedCorrection {
+        match self.collect_suggestions(word, n_suggestions) {
+            Some(suggestions) => SuggestedCorrection::Suggestions(suggestions),
+            None => SuggestedCorrection::NoSuggestions,
+        }
+    }
+
+    /// Like `suggest_single_word_corrections`, but only suggests words within
+    /// `max_edit_distance` of `word`, for queries that need higher precision
+    /// than the corrector's configured distance gives, e.g. strict checking
+    /// of short form fields next to lenient search queries. Distances above
+    /// the configured one are capped to it, since the index cannot find
+    /// farther words. Correction overrides and abbreviation expansions still
+    /// apply whatever their distance.
+    pub fn suggest_with_max_edit_distance(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+        max_edit_distance: usize,
+    ) -> SuggestedCorrection {
+        let max_edit_distance = max_edit_distance.min(self.max_edit_distance);
+        match self.collect_suggestions_within(word, n_suggestions, max_edit_distance) {
+            Some(suggestions) => SuggestedCorrection::Suggestions(suggestions),
+            None => SuggestedCorrection::NoSuggestions,
+        }
+    }
+
+    /// Queries each of `correctors` (e.g. a base dictionary and a domain
+    /// one) and merges their suggestions into one list, ranked by distance,
+    /// then frequency, then position in `correctors`, earlier first. A word
+    /// suggested by several correctors appears once, with its best ranking.
+    ///
+    /// `word` is correct, giving `NoSuggestions`, if any corrector has it.
+    /// Frequencies are compared as they are, so correctors built from
+    /// differently sized corpora may need their counts scaled first.
+    pub fn suggest_merged(
+        word: &str,
+        n_suggestions: usize,
+        correctors: &[&SpellCorrector<D>],
+    ) -> SuggestedCorrection {
+        let mut merged = Vec::new();
+        for (priority, corrector) in correctors.iter().enumerate() {
+            match corrector.collect_suggestions::<Vec<Suggestion>>(word, n_suggestions) {
+                Some(suggestions) => merged.extend(suggestions.into_iter().map(|s| (priority, s))),
+                None => return SuggestedCorrection::NoSuggestions,
+            }
+        }
+        merged.sort_by(|(pa, a), (pb, b)| {
+            a.distance
+                .cmp(&b.distance)
+                .then_with(|| b.frequency.cmp(&a.frequency))
+                .then_with(|| pa.cmp(pb))
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        let mut seen = HashSet::new();
+        let suggestions = merged
+            .into_iter()
+            .map(|(_, s)| s)
+            .filter(|s| seen.insert(s.word.clone()))
+            .take(n_suggestions)
+            .collect();
+        SuggestedCorrection::Suggestions(suggestions)
+    }
+
+    /// Like `suggest_single_word_corrections`, but returns the suggestions in
+    /// a `SmallSuggestions`, which keeps up to five of them without a heap
+    /// allocation. `None` means `word` is spelled correctly.
+    #[cfg(feature = "smallvec")]
+    #[must_use]
+    pub fn suggest_single_word_corrections_small(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+    ) -> Option<SmallSuggestions> {
+        self.collect_suggestions(word, n_suggestions)
+    }
+
+    // The shared query path: `None` for a correctly spelled word, otherwise
+    // the ranked suggestions collected into whichever container the caller
+    // wants, so cache hits only allocate what that container needs.
+    fn collect_suggestions<C>(&self, word: &str, n_suggestions: usize) -> Option<C>
+    where
+        C: FromIterator<Suggestion>,
+    {
+        self.collect_suggestions_within(word, n_suggestions, self.max_edit_distance)
+    }
+
+    // `collect_suggestions` limited to `max_edit_distance`, at most the
+    // configured one.
+    fn collect_suggestions_within<C>(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+        max_edit_distance: usize,
+    ) -> Option<C>
+    where
+        C: FromIterator<Suggestion>,
+    {
+        let word = self.normalize_query(word);
+        let word = word.as_ref();
+        let suggestions = match self.pre_search(word, n_suggestions, max_edit_distance) {
+            PreSearch::Correct => return None,
+            PreSearch::Answered(suggestions) => self.padded(word, suggestions, n_suggestions),
+            PreSearch::Search => self.cached_search(word, n_suggestions, max_edit_distance),
+        };
+        Some(suggestions.into_iter().collect())
+    }
+
+    // The ranked, truncated suggestions for a normalized `word` that
+    // `pre_search` left to the index, from the caches if they have them.
+    fn cached_search(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+        max_edit_distance: usize,
+    ) -> Vec<Suggestion> {
+        // `cachers` looks keys up by `&K`, so the owned word is built once and
+        // shared by both caches rather than allocated per lookup
+        let cache_key = (word.to_string(), n_suggestions, max_edit_distance);
+        if let Some(negative_cache) = &self.negative_cache
+            && negative_cache.get(&cache_key.0).is_some()
+        {
+            #[cfg(feature = "logging")]
+            log::trace!("negative cache hit for {:?}", word);
+            self.cache_hits.fetch_add(1, AtomicOrdering::Relaxed);
+            return self.padded(word, Vec::new(), n_suggestions);
+        }
+
+        if let Some(cached_suggestions) = self.suggestion_cache().get(&cache_key) {
+            #[cfg(feature = "logging")]
+            log::trace!("suggestion cache hit for {:?}", word);
+            self.cache_hits.fetch_add(1, AtomicOrdering::Relaxed);
+            return cached_suggestions.to_vec();
+        }
+        #[cfg(feature = "logging")]
+        log::trace!("suggestion cache miss for {:?}", word);
+        self.cache_misses.fetch_add(1, AtomicOrdering::Relaxed);
+
+        let mut suggestions = self.search_suggestions(word, n_suggestions, max_edit_distance);
+        // nothing within a smaller distance says nothing about the full one
+        if suggestions.is_empty()
+            && max_edit_distance == self.max_edit_distance
+            && let Some(negative_cache) = &self.negative_cache
+        {
+            negative_cache.set(cache_key.0.clone(), ());
+        }
+
+        suggestions.truncate(n_suggestions);
+        let suggestions = self.padded(word, suggestions, n_suggestions);
+
+        self.suggestion_cache().set(cache_key, suggestions.clone());
+
+        suggestions
+    }
+
+    // Everything a query for the normalized `word` checks before the caches
+    // and the index: correction overrides, abbreviation expansions, the
+    // dictionary itself, phrase handling, accent and hyphen folding and
+    // pinned queries, in that order.
+    fn pre_search(&self, word: &str, n_suggestions: usize, max_edit_distance: usize) -> PreSearch {
+        if let Some(target) = self.correction_overrides.get(word) {
+            return PreSearch::Answered(vec![self.override_suggestion(word, target)]);
+        }
+        if let Some(fulls) = self.expansions.get(word) {
+            return PreSearch::Answered(
+                fulls
+                    .iter()
+                    .take(n_suggestions)
+                    .map(|full| self.override_suggestion(word, full))
+                    .collect(),
+            );
+        }
+        if !self.real_word_mode && self.dictionary.contains(word) {
+            return PreSearch::Correct;
+        }
+        if word.contains(char::is_whitespace) {
+            let Some(mut suggestions) = self.phrase_suggestions(word, max_edit_distance) else {
+                return PreSearch::Correct;
+            };
+            // the dictionary may hold multi-word entries, e.g. "san francisco"
+            suggestions.extend(self.search_suggestions(word, n_suggestions, max_edit_distance));
+            if self.space_edits && max_edit_distance > 0 {
+                suggestions.extend(self.space_joins(word));
+            }
+            self.rank_suggestions(word, &mut suggestions);
+            let mut seen = HashSet::new();
+            suggestions.retain(|s| seen.insert(s.word.clone()));
+            suggestions.truncate(n_suggestions);
+            return PreSearch::Answered(suggestions);
+        }
+        if let Some(mut suggestions) = self.folded_matches(word) {
+            suggestions.truncate(n_suggestions);
+            return PreSearch::Answered(suggestions);
+        }
+        if let Some(pinned) = self.pinned.get(word) {
+            let pinned = match pinned.get() {
+                Some(pinned) => {
+                    self.cache_hits.fetch_add(1, AtomicOrdering::Relaxed);
+                    pinned
+                }
+                None => {
+                    self.cache_misses.fetch_add(1, AtomicOrdering::Relaxed);
+                    pinned.get_or_init(|| {
+                        self.search_suggestions(word, usize::MAX, self.max_edit_distance)
+                    })
+                }
+            };
+            return PreSearch::Answered(
+                pinned
+                    .iter()
+                    .filter(|s| s.distance <= max_edit_distance)
+                    .take(n_suggestions)
+                    .cloned()
+                    .collect(),
+            );
+        }
+        PreSearch::Search
+    }
+
+    // A query with whitespace in it is usually a phrase, not a word: unless
+    // the dictionary has multi-word entries, deletion variants spanning the
+    // gap match nothing useful. Each word is checked on its own as well;
+    // `None` if all are correct, otherwise one suggestion
+    // joining the words with single spaces, misspelled ones replaced by their
+    // best correction, at the summed distance. Words without a correction are
+    // kept as written; if none has one there are no suggestions.
+    fn phrase_suggestions(
+        &self,
+        phrase: &str,
+        max_edit_distance: usize,
+    ) -> Option<Vec<Suggestion>> {
+        let mut misspelled = false;
+        let mut corrected = false;
+        let mut distance = 0;
+        let words: Vec<Cow<'_, str>> = phrase
+            .split_whitespace()
+            .map(|word| {
+                let Some(suggestions) =
+                    self.collect_suggestions_within::<Vec<Suggestion>>(word, 1, max_edit_distance)
+                else {
+                    return Cow::Borrowed(word);
+                };
+                misspelled = true;
+                match suggestions.into_iter().next() {
+                    Some(best) => {
+                        corrected = true;
+                        distance += best.distance;
+                        Cow::Owned(best.word)
+                    }
+                    None => Cow::Borrowed(word),
+                }
+            })
+            .collect();
+        if !misspelled {
+            return None;
+        }
+        let suggestion = corrected.then(|| Suggestion {
+            word: words.join(" "),
+            distance,
+            frequency: 0,
+            metadata: None,
+        });
+        Some(suggestion.into_iter().collect())
+    }
+
+    // `suggestions` for `word`, filled up to `n_suggestions` with the nearest
+    // words beyond them if `with_suggestion_padding` is on.
+    fn padded(
+        &self,
+        word: &str,
+        mut suggestions: Vec<Suggestion>,
+        n_suggestions: usize,
+    ) -> Vec<Suggestion> {
+        if self.pad_suggestions && suggestions.len() < n_suggestions {
+            self.pad_with_nearest(word, &mut suggestions, n_suggestions);
+        }
+        suggestions
+    }
+
+    // Appends the dictionary words nearest to `word` that are not already
+    // suggested, by brute force, until there are `n_suggestions`, for
+    // `with_suggestion_padding`. They are held to the same frequency and
+    // length limits as searched words, and to the length ratio guard, since
+    // far-off words are the ones it exists to catch.
+    fn pad_with_nearest(
+        &self,
+        word: &str,
+        suggestions: &mut Vec<Suggestion>,
+        n_suggestions: usize,
+    ) {
+        let taken: HashSet<String> = suggestions.iter().map(|s| s.word.clone()).collect();
+        let mut extra: Vec<Suggestion> = (0..self.dictionary.len())
+            .filter(|&index| self.dictionary.frequency(index) >= self.min_frequency)
+            .filter_map(|index| {
+                let candidate = self.word_at(index);
+                if taken.contains(candidate.as_ref())
+                    || (self.exclude_input && candidate == word)
+                    || self
+                        .max_suggestion_len
+                        .is_some_and(|cap| candidate.chars().count() > cap)
+                    || !self.passes_length_ratio_guard(word, &candidate)
+                {
+                    return None;
+                }
+                let max_dist = word.len().max(candidate.len());
+                Some(Suggestion {
+                    distance: self.distance_within(word, &candidate, max_dist),
+                    frequency: self.dictionary.frequency(index),
+                    metadata: self.word_metadata.get(candidate.as_ref()).cloned(),
+                    word: candidate.into_owned(),
+                })
+            })
+            .collect();
+        self.rank_suggestions(word, &mut extra);
+        suggestions.extend(extra.into_iter().take(n_suggestions - suggestions.len()));
+    }
+
+    // `word` with a space inserted where both sides are dictionary words,
+    // for `with_space_edits`.
+    fn space_splits(&self, word: &str) -> Vec<Suggestion> {
+        word.char_indices()
+            .skip(1)
+            .filter_map(|(split, _)| {
+                let (left, right) = word.split_at(split);
+                (self.dictionary.contains(left) && self.dictionary.contains(right)).then(|| {
+                    Suggestion {
+                        word: format!("{left} {right}"),
+                        distance: 1,
+                        frequency: self.frequency_of(left).min(self.frequency_of(right)),
+                        metadata: None,
+                    }
+                })
+            })
+            .collect()
+    }
+
+    // `phrase` with the space between two of its words removed, where the
+    // joined word is in the dictionary but one of its parts is not, for
+    // `with_space_edits`.
+    fn space_joins(&self, phrase: &str) -> Vec<Suggestion> {
+        let words: Vec<&str> = phrase.split_whitespace().collect();
+        (1..words.len())
+            .filter_map(|gap| {
+                let (left, right) = (words[gap - 1], words[gap]);
+                let joined = format!("{left}{right}");
+                let misspelled =
+                    !self.dictionary.contains(left) || !self.dictionary.contains(right);
+                (misspelled && self.dictionary.contains(&joined)).then(|| {
+                    let frequency = self.frequency_of(&joined);
+                    let mut parts = words[..gap - 1].to_vec();
+                    parts.push(&joined);
+                    parts.extend(&words[gap + 1..]);
+                    Suggestion {
+                        word: parts.join(" "),
+                        distance: 1,
+                        frequency,
+                        metadata: self.word_metadata.get(&joined).cloned(),
+                    }
+                })
+            })
+            .collect()
+    }
+
+    // Searches the index for `word` up to `max_edit_distance`, bypassing the
+    // caches: the ranked suggestions, of which at least the first
+    // `n_suggestions` are final (the fast path may leave out farther ones).
+    fn search_suggestions(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+        max_edit_distance: usize,
+    ) -> Vec<Suggestion> {
+        let mut suggestions = if self.wildcards && word.contains([WILDCARD_ANY, WILDCARD_OPTIONAL])
+        {
+            self.wildcard_matches(word, max_edit_distance)
+        } else {
+            let fast = (max_edit_distance > 0)
+                .then(|| self.one_deletion_fast_path(word, n_suggestions))
+                .flatten();
+            match fast {
+                Some(suggestions) => suggestions,
+                None => self.verified_candidates(word, max_edit_distance),
+            }
+        };
+        if self.space_edits && max_edit_distance > 0 {
+            suggestions.extend(self.space_splits(word));
+        }
+        if self.real_word_mode && self.exclude_input {
+            suggestions.retain(|s| s.word != word);
+        }
+        suggestions.retain(|s| s.frequency >= self.min_frequency);
+        self.rank_suggestions(word, &mut suggestions);
+        suggestions
+    }
+
+    /// Like `suggest_single_word_corrections`, but moves one of the top
+    /// `n_suggestions`, picked uniformly with `rng`, to the front; the rest
+    /// keep their order. For A/B tests of suggestion acceptance, e.g. with
+    /// `n_suggestions = 2` the runner-up is shown first half the time. Seed
+    /// `rng` to make the picks reproducible.
+    #[cfg(feature = "sampling")]
+    pub fn suggest_sampled<R: rand::Rng + ?Sized>(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+        rng: &mut R,
+    ) -> SuggestedCorrection {
+        match self.suggest_single_word_corrections(word, n_suggestions) {
+            SuggestedCorrection::Suggestions(mut list) if !list.is_empty() => {
+                let pick = rng.gen_range(0..list.len());
+                list[..=pick].rotate_right(1);
+                SuggestedCorrection::Suggestions(list)
+            }
+            other => other,
+        }
+    }
+
+    /// Calls `f` with each ranked suggestion for `word`, best first. Nothing
+    /// is called when `word` is correct or has no suggestions.
+    pub fn for_each_suggestion<F>(&self, word: &str, n_suggestions: usize, mut f: F)
+    where
+        F: FnMut(&Suggestion),
+    {
+        if let SuggestedCorrection::Suggestions(list) =
+            self.suggest_single_word_corrections(word, n_suggestions)
+        {
+            list.iter().for_each(&mut f);
+        }
+    }
+
+    /// Returns every candidate at the smallest edit distance found for `word`,
+    /// rather than an arbitrary `n` of them.
+    ///
+    /// Goes through the same checks and filters as
+    /// `suggest_single_word_corrections` and orders candidates the same way,
+    /// but none are dropped by the length/alphabetical tie-breaks. Padding
+    /// does not apply, since the tier is never cut short.
+    pub fn suggest_best_tier(&self, word: &str) -> SuggestedCorrection {
+        let word = self.normalize_query(word);
+        let word = word.as_ref();
+        let mut suggestions = match self.pre_search(word, usize::MAX, self.max_edit_distance) {
+            PreSearch::Correct => return SuggestedCorrection::NoSuggestions,
+            PreSearch::Answered(suggestions) => suggestions,
+            PreSearch::Search => self.search_suggestions(word, usize::MAX, self.max_edit_distance),
+        };
+        if let Some(best) = suggestions.iter().map(|s| s.distance).min() {
+            suggestions.retain(|s| s.distance == best);
+        }
+
+        SuggestedCorrection::Suggestions(suggestions)
+    }
+
+    // Candidates sharing a deletion variant with `word` (or with one of its
+    // confusion-set expansions), kept only if they are within
+    // `max_edit_distance` (at most the configured one). Order is unspecified.
+    fn verified_candidates(&self, word: &str, max_edit_distance: usize) -> Vec<Suggestion> {
+        if self.confusion_set.is_empty() {
+            let query_len = word.chars().count();
+            return self
+                .candidate_indices(word, max_edit_distance)
+                .into_iter()
+                .filter_map(|candidate| {
+                    let candidate_word = self.word_at(candidate);
+                    let max_dist = self.max_distance_for(candidate).min(max_edit_distance);
+                    if !self.length_within_reach(query_len, &candidate_word, max_dist) {
+                        return None;
+                    }
+                    let distance = self.distance_within(word, &candidate_word, max_dist);
+                    if distance <= max_dist {
+                        Some(self.suggestion_for(candidate, distance))
+                    } else {
+                        None
+                    }
+                })
+                .collect();
+        }
+
+        let mut best: HashMap<usize, usize> = HashMap::new();
+        for variant in self.confusion_variants(word) {
+            let query_len = variant.chars().count();
+            for candidate in self.candidate_indices(&variant, max_edit_distance) {
+                let candidate_word = self.word_at(candidate);
+                let max_dist = self.max_distance_for(candidate).min(max_edit_distance);
+                if !self.length_within_reach(query_len, &candidate_word, max_dist) {
+                    continue;
+                }
+                let distance = self.distance_within(&variant, &candidate_word, max_dist);
+                if distance <= max_dist {
+                    let entry = best.entry(candidate).or_insert(distance);
+                    *entry = (*entry).min(distance);
+                }
+            }
+        }
+        let mut best: Vec<(usize, usize)> = best.into_iter().collect();
+        best.sort_unstable();
+        best.into_iter()
+            .map(|(candidate, distance)| self.suggestion_for(candidate, distance))
+            .collect()
+    }
+
+    // Whether `candidate` is short enough to be within `max_dist` edits of a
+    // query of `query_len` chars, and within the `max_suggestion_len` cap.
+    fn length_within_reach(&self, query_len: usize, candidate: &str, max_dist: usize) -> bool {
+        let len = candidate.chars().count();
+        len <= query_len + max_dist && self.max_suggestion_len.is_none_or(|cap| len <= cap)
+    }
+
+    // Scans the whole dictionary, skipping words too short or too long to be
+    // within `max_edit_distance` of `pattern`.
+    fn wildcard_matches(&self, pattern: &str, max_edit_distance: usize) -> Vec<Suggestion> {
+        let optional = pattern.matches(WILDCARD_OPTIONAL).count();
+        let required = pattern.chars().count() - optional;
+        let min_len = required.saturating_sub(max_edit_distance);
+        let max_len = required + optional + max_edit_distance;
+        let max_len = self
+            .max_suggestion_len
+            .map_or(max_len, |cap| cap.min(max_len));
+        (0..self.dictionary.len())
+            .filter_map(|candidate| {
+                let word = self.word_at(candidate);
+                if !(min_len..=max_len).contains(&word.chars().count()) {
+                    return None;
+                }
+                let max_dist = self.max_distance_for(candidate).min(max_edit_distance);
+                let distance = wildcard_distance(pattern, &word, max_dist);
+                (distance <= max_dist).then(|| self.suggestion_for(candidate, distance))
+            })
+            .collect()
+    }
+
+    // Most typos are a single stray character. If deleting one character of
+    // `word` gives a dictionary word, nothing can beat distance 1, so only the
+    // distance-1 candidates are collected (a depth-1 scan instead of the full
+    // `max_edit_distance` one). That premise fails when `word` is itself a
+    // dictionary word that real-word mode keeps in the list at distance 0.
+    // Returns `None` if the shortcut does not apply or finds fewer than
+    // `n_suggestions` matches, in which case the full scan is still needed to
+    // fill the list with farther candidates.
+    fn one_deletion_fast_path(&self, word: &str, n_suggestions: usize) -> Option<Vec<Suggestion>> {
+        if self.max_edit_distance == 0
+            || self.distance_fn.is_some()
+            || !self.confusion_set.is_empty()
+            || self.normalized_ranking
+            || self.error_model.is_some()
+            || self.min_frequency > 0
+            || self.space_edits
+            || self.pad_suggestions
+            || (self.real_word_mode && !self.exclude_input)
+            || self.acceptances.contains_key(word)
+        {
+            return None;
+        }
+        let one_deletions = deletion_variants(word, 1, false);
+        if !one_deletions.iter().any(|w| self.dictionary.contains(w)) {
+            return None;
+        }
+
+        let mut candidates = Vec::new();
+        for del_word in one_deletions.iter().map(String::as_str).chain([word]) {
+            candidates.extend(self.dictionary.deletion_candidates(del_word).iter());
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        let suggestions: Vec<Suggestion> = candidates
+            .into_iter()
+            .filter(|&candidate| {
+                let candidate_word = self.word_at(candidate);
+                self.max_distance_for(candidate) >= 1
+                    && self
+                        .max_suggestion_len
+                        .is_none_or(|cap| candidate_word.chars().count() <= cap)
+                    && bounded_levenshtein(word, &candidate_word, 1) == 1
+            })
+            .map(|candidate| self.suggestion_for(candidate, 1))
+            .collect();
+        (suggestions.len() >= n_suggestions).then_some(suggestions)
+    }
+
+    // Words sharing a deletion variant of up to `depth` deletions with `word`;
+    // covers every word within `depth` edits as long as `depth` is at most
+    // `max_edit_distance`. Sorted by index, so candidates are verified and
+    // tied suggestions ranked in the same order on every run, whatever order
+    // the hash sets produce the deletion variants in.
+    fn candidate_indices(&self, word: &str, depth: usize) -> Vec<usize> {
+        // include the query itself: it may be a deletion of a dictionary word,
+        // e.g. "cat" of "cart", and then no shorter variant links the two
+        let word_deletions = deletion_variants(word, depth, true);
+        let mut candidates = Vec::new();
+
+        for del_word in &word_deletions {
+            candidates.extend(self.dictionary.deletion_candidates(del_word).iter());
+        }
+        candidates.sort_unstable();
+        candidates.dedup();
+        #[cfg(feature = "logging")]
+        if let Some(threshold) = self.slow_query_candidates
+            && candidates.len() > threshold
+        {
+            log::warn!(
+                "slow query {:?}: {} candidates at depth {} (threshold {})",
+                word,
+                candidates.len(),
+                depth,
+                threshold
+            );
+        }
+        candidates
+    }
+
+    // `word` followed by every spelling reachable by replacing confusable
+    // substrings, capped at MAX_CONFUSION_VARIANTS.
+    fn confusion_variants(&self, word: &str) -> Vec<String> {
+        let mut variants = vec![word.to_string()];
+        let mut seen: HashSet<String> = variants.iter().cloned().collect();
+        let mut i = 0;
+        while i < variants.len() && variants.len() < MAX_CONFUSION_VARIANTS {
+            let current = variants[i].clone();
+            for (pos, _) in current.char_indices() {
+                for (from, replacements) in &self.confusion_set {
+                    if !current[pos..].starts_with(from.as_str()) {
+                        continue;
+                    }
+                    for to in replacements {
+                        let variant =
+                            format!("{}{}{}", &current[..pos], to, &current[pos + from.len()..]);
+                        if variants.len() < MAX_CONFUSION_VARIANTS && seen.insert(variant.clone()) {
+                            variants.push(variant);
+                        }
+                    }
+                }
+            }
+            i += 1;
+        }
+        variants
+    }
+
+    fn suggestion_for(&self, index: usize, distance: usize) -> Suggestion {
+        let word = self.word_at(index).into_owned();
+        Suggestion {
+            metadata: self.word_metadata.get(&word).cloned(),
+            word,
+            distance,
+            frequency: self.dictionary.frequency(index),
+        }
+    }
+
+    // Candidate indices come from the dictionary itself, so a missing word
+    // only happens with an inconsistent custom `Dictionary`.
+    fn word_at(&self, index: usize) -> Cow<'_, str> {
+        self.dictionary.get(index).unwrap_or_default()
+    }
+
+    fn rank_suggestions(&self, query: &str, suggestions: &mut [Suggestion]) {
+        let query_len = query.chars().count();
+        if let Some(model) = &self.error_model {
+            // log P(word) + log P(query | word); the smoothing denominator
+            // is the same for every candidate, so it is left out
+            let scores: HashMap<String, f64> = suggestions
+                .iter()
+                .map(|s| {
+                    let prior = (s.frequency as f64 + 1.0).ln();
+                    let score = prior + channel_log_probability(query, &s.word, model);
+                    (s.word.clone(), score)
+                })
+                .collect();
+            suggestions.sort_by(|a, b| {
+                scores[&b.word]
+                    .total_cmp(&scores[&a.word])
+                    .then_with(|| self.compare_suggestions(query, query_len, a, b))
+            });
+        } else {
+            suggestions.sort_by(|a, b| self.compare_suggestions(query, query_len, a, b));
+        }
+        if let Some(accepted) = self.acceptances.get(query) {
+            // stable, so the rest keep their order
+            suggestions.sort_by_key(|s| Reverse(accepted.get(&s.word).copied().unwrap_or(0)));
+        }
+    }
+
+    fn compare_suggestions(
+        &self,
+        query: &str,
+        query_len: usize,
+        a: &Suggestion,
+        b: &Suggestion,
+    ) -> Ordering {
+        if self.normalized_ranking {
+            // compares a.distance / a_len with b.distance / b_len without floats
+            let a_len = a.word.chars().count().max(1);
+            let b_len = b.word.chars().count().max(1);
+            let order = (a.distance * b_len).cmp(&(b.distance * a_len));
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+        if self.lcs_tie_break && a.distance == b.distance {
+            let order = longest_common_subsequence(query, &b.word)
+                .cmp(&longest_common_subsequence(query, &a.word));
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+        if self.position_weighted_ranking && a.distance == b.distance {
+            let order =
+                edit_position_penalty(query, &a.word).cmp(&edit_position_penalty(query, &b.word));
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+        if self.phonetic_tie_break && a.distance == b.distance && a.frequency == b.frequency {
+            let key = metaphone(query);
+            let order = (metaphone(&b.word) == key).cmp(&(metaphone(&a.word) == key));
+            if order != Ordering::Equal {
+                return order;
+            }
+        }
+        default_suggestion_order(query_len, a, b)
+    }
+
+    /// The deletion variants a query for `word` looks up in the index, up to
+    /// `max_edit_distance` deletions and not counting `word` itself, which is
+    /// looked up too. `word` is normalized the way queries are. For tracing
+    /// why a word is or is not found: pass each variant to
+    /// `deletion_key_words` to see what it reaches, or use `trace` for the
+    /// key that linked each suggestion.
+    #[must_use]
+    pub fn query_deletion_variants(&self, word: &str) -> HashSet<String> {
+        deletion_variants(&self.normalize_query(word), self.max_edit_distance, false)
+    }
+
+    /// The dictionary words the deletion index maps `key` to, in index order.
+    /// Empty if no word has `key` as a deletion variant.
+    #[must_use]
+    pub fn deletion_key_words(&self, key: &str) -> Vec<String> {
+        self.dictionary
+            .deletion_candidates(key)
+            .iter()
+            .map(|&index| self.word_at(index).into_owned())
+            .collect()
+    }
+
+    /// Runs a query with diagnostics, bypassing the caches.
+    ///
+    /// `candidates_considered` counts the dictionary words that shared a
+    /// deletion variant with the query and so had their distance computed;
+    /// unusually high counts point at expensive queries.
+    ///
+    /// Each returned suggestion carries the deletion key that linked it to the
+    /// query: a deletion variant of the query shared with the candidate. When
+    /// several keys link the same pair the longest (fewest deletions) is
+    /// reported, ties broken alphabetically.
+    #[must_use]
+    pub fn trace(&self, word: &str, n_suggestions: usize) -> QueryTrace {
+        let word = self.normalize_query(word);
+        let word = word.as_ref();
+        let mut trace = QueryTrace {
+            query: word.to_string(),
+            in_dictionary: self.dictionary.contains(word),
+            candidates_considered: 0,
+            suggestions: Vec::new(),
+        };
+        if trace.in_dictionary {
+            return trace;
+        }
+
+        let word_deletions = deletion_variants(word, self.max_edit_distance, true);
+        let mut linking_keys: HashMap<usize, &str> = HashMap::new();
+        for del_word in &word_deletions {
+            for &candidate in self.dictionary.deletion_candidates(del_word).iter() {
+                let key = linking_keys.entry(candidate).or_insert(del_word);
+                if (del_word.len(), Reverse(del_word.as_str())) > (key.len(), Reverse(*key)) {
+                    *key = del_word;
+                }
+            }
+        }
+
+        trace.candidates_considered = linking_keys.len();
+        trace.suggestions = linking_keys
+            .into_iter()
+            .filter_map(|(candidate, key)| {
+                let distance = self.distance(word, &self.word_at(candidate));
+                (distance <= self.max_distance_for(candidate)).then(|| TracedSuggestion {
+                    suggestion: self.suggestion_for(candidate, distance),
+                    matched_key: key.to_string(),
+                })
+            })
+            .collect();
+        let query_len = word.chars().count();
+        trace.suggestions.sort_by(|a, b| {
+            self.compare_suggestions(word, query_len, &a.suggestion, &b.suggestion)
+        });
+        trace.suggestions.truncate(n_suggestions);
+        trace
+    }
+
+    /// Counts the dictionary words within `max_dist` edits of `word`,
+    /// including `word` itself if it is in the dictionary, without building
+    /// suggestions for them. High counts flag ambiguous or garbled queries.
+    ///
+    /// `max_dist` is capped at the corrector's `max_edit_distance`, the
+    /// furthest the deletion index can reach. Per-word limits are ignored.
+    #[must_use]
+    pub fn num_candidates_within(&self, word: &str, max_dist: usize) -> usize {
+        let word = self.normalize_query(word);
+        let word = word.as_ref();
+        let max_dist = max_dist.min(self.max_edit_distance);
+        self.candidate_indices(word, max_dist)
+            .into_iter()
+            .filter(|&candidate| {
+                self.distance_within(word, &self.word_at(candidate), max_dist) <= max_dist
+            })
+            .count()
+    }
+
+    /// The `n` dictionary words with the most other dictionary words within
+    /// `max_dist` edits, with those neighbour counts, most confusable first.
+    /// A misspelling of one of them is likely to be "corrected" to a
+    /// neighbour, so these are the entries worth reviewing when curating a
+    /// dictionary. Words without neighbours are left out.
+    ///
+    /// Runs a candidate scan for every word, so it takes about as long as
+    /// correcting the whole dictionary.
+    #[must_use]
+    pub fn most_ambiguous_words(&self, max_dist: usize, n: usize) -> Vec<(String, usize)> {
+        let mut counts: Vec<(String, usize)> = (0..self.dictionary.len())
+            .into_par_iter()
+            .with_min_len(self.min_batch_len)
+            .filter_map(|index| {
+                let word = self.word_at(index);
+                // the word itself is among its own candidates
+                let neighbours = self
+                    .num_candidates_within(&word, max_dist)
+                    .saturating_sub(1);
+                (neighbours > 0).then(|| (word.into_owned(), neighbours))
+            })
+            .collect();
+        counts.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        counts.truncate(n);
+        counts
+    }
+
+    /// The smallest edit distance from `word` to a dictionary word (0 if it
+    /// is one), or `None` if nothing is within `max_edit_distance`.
+    ///
+    /// Meant for gating ("is there a one-edit fix?"): it searches one edit
+    /// deeper at a time and stops at the first depth with a match, without
+    /// building or sorting suggestions.
+    #[must_use]
+    pub fn min_distance_candidate(&self, word: &str) -> Option<usize> {
+        let word = self.normalize_query(word);
+        let word = word.as_ref();
+        if self.dictionary.contains(word) {
+            return Some(0);
+        }
+        let mut nearest = None;
+        for depth in 1..=self.max_edit_distance {
+            // deeper searches see a superset of the candidates, so each
+            // depth's nearest supersedes the last
+            nearest = self
+                .candidate_indices(word, depth)
+                .into_iter()
+                .filter_map(|candidate| {
+                    let max_dist = self.max_distance_for(candidate).min(self.max_edit_distance);
+                    let distance = self.distance_within(word, &self.word_at(candidate), max_dist);
+                    (distance <= max_dist).then_some(distance)
+                })
+                .min();
+            // every word `depth` edits away shares a deletion with the query
+            // at this depth, so nothing closer is left to find
+            if nearest.is_some_and(|distance| distance <= depth) {
+                break;
+            }
+        }
+        nearest
+    }
+
+    /// Like `suggest_single_word_corrections`, but searches one edit deeper
+    /// at a time and hands each tier of new matches to `on_tier` as soon as
+    /// it is found, as `on_tier(distance, tier)` with the tier ranked, so a
+    /// UI can show the close matches before the slower, deeper search ends.
+    /// The search stops once `n_suggestions` matches are known.
+    ///
+    /// Returns the combined ranked list, like the non-progressive query, and
+    /// goes through the same checks first: a query answered by a correction
+    /// override, an abbreviation expansion, phrase handling, folding, a pin
+    /// or the negative cache is handed over at once, one tier per distance.
+    /// Each tier after that is searched with every setting the plain query
+    /// honours (confusion set, wildcards, real-word mode, frequency and
+    /// length filters, space edits), and padding, if enabled, arrives last.
+    /// Tiers with no matches are skipped, and a correctly spelled word gets
+    /// `NoSuggestions` without any callback. With the built-in metric the
+    /// tier at depth `d` holds the words exactly `d` edits away; a custom
+    /// `DistanceFn` may place closer words in a later tier. Bypasses the
+    /// suggestion cache.
+    pub fn suggest_progressive<F>(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+        mut on_tier: F,
+    ) -> SuggestedCorrection
+    where
+        F: FnMut(usize, &[Suggestion]),
+    {
+        let word = self.normalize_query(word);
+        let word = word.as_ref();
+        match self.pre_search(word, n_suggestions, self.max_edit_distance) {
+            PreSearch::Correct => return SuggestedCorrection::NoSuggestions,
+            PreSearch::Answered(suggestions) => {
+                let suggestions = self.padded(word, suggestions, n_suggestions);
+                hand_over_tiers(&suggestions, &mut on_tier);
+                return SuggestedCorrection::Suggestions(suggestions);
+            }
+            PreSearch::Search => {}
+        }
+        if let Some(negative_cache) = &self.negative_cache
+            && negative_cache.get(&word.to_string()).is_some()
+        {
+            self.cache_hits.fetch_add(1, AtomicOrdering::Relaxed);
+            let suggestions = self.padded(word, Vec::new(), n_suggestions);
+            hand_over_tiers(&suggestions, &mut on_tier);
+            return SuggestedCorrection::Suggestions(suggestions);
+        }
+
+        let mut found: HashSet<String> = HashSet::new();
+        let mut suggestions = Vec::new();
+        for depth in 1..=self.max_edit_distance {
+            if suggestions.len() >= n_suggestions {
+                break;
+            }
+            let mut tier = self.search_suggestions(word, usize::MAX, depth);
+            tier.retain(|s| !found.contains(&s.word));
+            if tier.is_empty() {
+                continue;
+            }
+            found.extend(tier.iter().map(|s| s.word.clone()));
+            on_tier(depth, &tier);
+            suggestions.extend(tier);
+        }
+        self.rank_suggestions(word, &mut suggestions);
+        suggestions.truncate(n_suggestions);
+        let searched = suggestions.len();
+        let suggestions = self.padded(word, suggestions, n_suggestions);
+        hand_over_tiers(&suggestions[searched..], &mut on_tier);
+        SuggestedCorrection::Suggestions(suggestions)
+    }
+
+    #[must_use]
+    pub fn suggest_word_corrections(
+        &self,
+        words: &Vec<String>,
+        n_suggestions: usize,
+    ) -> Vec<SuggestedCorrection> {
+        if words.len() < self.parallel_threshold {
+            return words
+                .iter()
+                .map(|word| self.suggest_single_word_corrections(word, n_suggestions))
+                .collect();
+        }
+        words
+            .par_iter()
+            .with_min_len(self.min_batch_len)
+            .map(|word| self.suggest_single_word_corrections(word, n_suggestions))
+            .collect()
+    }
+
+    /// Like `suggest_word_corrections`, but returns only the words that have
+    /// suggestions, each with its position in `words`, in order. Correct words
+    /// and words with nothing close enough are left out, which keeps results
+    /// for long, mostly correct documents small.
+    #[must_use]
+    pub fn suggest_corrections_sparse(
+        &self,
+        words: &[String],
+        n_suggestions: usize,
+    ) -> Vec<(usize, SuggestedCorrection)> {
+        let suggest = |(i, word): (usize, &String)| {
+            let result = self.suggest_single_word_corrections(word, n_suggestions);
+            (!result.suggestions().is_empty()).then_some((i, result))
+        };
+        if words.len() < self.parallel_threshold {
+            return words.iter().enumerate().filter_map(suggest).collect();
+        }
+        words
+            .par_iter()
+            .with_min_len(self.min_batch_len)
+            .enumerate()
+            .filter_map(suggest)
+            .collect()
+    }
+
+    /// Batch form of `suggest_single_word_corrections_small`.
+    #[cfg(feature = "smallvec")]
+    #[must_use]
+    pub fn suggest_word_corrections_small(
+        &self,
+        words: &[String],
+        n_suggestions: usize,
+    ) -> Vec<Option<SmallSuggestions>> {
+        if words.len() < self.parallel_threshold {
+            return words
+                .iter()
+                .map(|word| self.suggest_single_word_corrections_small(word, n_suggestions))
+                .collect();
+        }
+        words
+            .par_iter()
+            .with_min_len(self.min_batch_len)
+            .map(|word| self.suggest_single_word_corrections_small(word, n_suggestions))
+            .collect()
+    }
+
+    /// The top suggestion for `word`, or `None` if it is spelled correctly, has
+    /// no suggestions, or its best one is rejected by the length-ratio guard.
+    #[must_use]
+    pub fn best_correction(&self, word: &str) -> Option<Suggestion> {
+        match self.suggest_single_word_corrections(word, 1) {
+            SuggestedCorrection::Suggestions(list) => list
+                .into_iter()
+                .next()
+                .filter(|best| self.passes_length_ratio_guard(word, &best.word)),
+            SuggestedCorrection::NoSuggestions => None,
+        }
+    }
+
+    /// Suggestions for a word still being typed: dictionary words starting
+    /// with `prefix`, most frequent first, then corrections of `prefix`
+    /// itself in case it is already misspelled. At most `n_suggestions` in
+    /// all, without duplicates.
+    ///
+    /// Each suggestion comes with `MatchKind::Completion` or
+    /// `MatchKind::Corrected`; a completion's `distance` is the number of
+    /// chars left to type. Completions come from a scan of the whole
+    /// dictionary, so this costs more than a plain query on large
+    /// dictionaries.
+    #[must_use]
+    pub fn complete_or_correct(
+        &self,
+        prefix: &str,
+        n_suggestions: usize,
+    ) -> Vec<(MatchKind, Suggestion)> {
+        let prefix = self.normalize_query(prefix);
+        let prefix = prefix.as_ref();
+        let prefix_len = prefix.chars().count();
+        let mut completions: Vec<Suggestion> = (0..self.dictionary.len())
+            .filter(|&index| self.dictionary.frequency(index) >= self.min_frequency)
+            .filter(|&index| {
+                let candidate = self.word_at(index);
+                candidate.starts_with(prefix)
+                    && self
+                        .max_suggestion_len
+                        .is_none_or(|cap| candidate.chars().count() <= cap)
+            })
+            .map(|index| {
+                let remaining = self.word_at(index).chars().count() - prefix_len;
+                self.suggestion_for(index, remaining)
+            })
+            .collect();
+        completions.sort_by(|a, b| {
+            b.frequency
+                .cmp(&a.frequency)
+                .then_with(|| a.distance.cmp(&b.distance))
+                .then_with(|| a.word.cmp(&b.word))
+        });
+        completions.truncate(n_suggestions);
+        let mut suggestions: Vec<(MatchKind, Suggestion)> = completions
+            .into_iter()
+            .map(|completion| (MatchKind::Completion, completion))
+            .collect();
+
+        if suggestions.len() < n_suggestions {
+            let corrections = self
+                .suggest_single_word_corrections(prefix, n_suggestions)
+                .into_suggestions();
+            for correction in corrections {
+                if suggestions.len() == n_suggestions {
+                    break;
+                }
+                if !suggestions.iter().any(|(_, s)| s.word == correction.word) {
+                    suggestions.push((MatchKind::Corrected, correction));
+                }
+            }
+        }
+        suggestions
+    }
+
+    /// `best_correction` together with a character diff from `word` to it,
+    /// for editors that render the fix inline.
+    #[must_use]
+    pub fn best_correction_diff(&self, word: &str) -> Option<(Suggestion, Vec<DiffSpan>)> {
+        let best = self.best_correction(word)?;
+        let diff = edit_diff(word, &best.word);
+        Some((best, diff))
+    }
+
+    /// Like `suggest_single_word_corrections`, but also says whether `word`
+    /// was correct as written, correct only after normalization, a registered
+    /// abbreviation, or needed correcting.
+    pub fn suggest_with_match_kind(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+    ) -> (MatchKind, SuggestedCorrection) {
+        let normalized = self.normalize_query(word);
+        let kind = if !self.correction_overrides.contains_key(normalized.as_ref())
+            && self.expansions.contains_key(normalized.as_ref())
+        {
+            MatchKind::Abbreviation
+        } else if self.dictionary.contains(word) {
+            MatchKind::Exact
+        } else if matches!(normalized, Cow::Owned(_)) && self.dictionary.contains(&normalized) {
+            MatchKind::Normalized
+        } else {
+            MatchKind::Corrected
+        };
+        (
+            kind,
+            self.suggest_single_word_corrections(word, n_suggestions),
+        )
+    }
+
+    /// Splits a run-on word into the two dictionary words it most likely
+    /// joins, e.g. "alot" into `("a", "lot")`. One half may instead be a
+    /// misspelling, longer than `max_edit_distance`, and is returned corrected.
+    /// Returns `None` if `word` is in the dictionary or has no such split.
+    ///
+    /// Splits needing fewer edits win, then those with more frequent halves.
+    #[must_use]
+    pub fn split_runon(&self, word: &str) -> Option<(String, String)> {
+        let word = self.normalize_query(word);
+        let word = word.as_ref();
+        if self.dictionary.contains(word) {
+            return None;
+        }
+        // (edits, score, left, right)
+        let mut best: Option<(usize, f64, String, String)> = None;
+        for (_, left, right, edits) in self.runon_splits(word) {
+            let score = self.split_score(&left, &right);
+            let better = match &best {
+                Some((best_edits, best_score, _, _)) => {
+                    edits < *best_edits || (edits == *best_edits && score > *best_score)
+                }
+                None => true,
+            };
+            if better {
+                best = Some((edits, score, left, right));
+            }
+        }
+        best.map(|(_, _, left, right)| (left, right))
+    }
+
+    /// Up to `n` ways to split a run-on word, for letting a user choose
+    /// between readings of an ambiguous one such as "nowhere". Each split is
+    /// found as in `split_runon`, but they are ranked by a single score: the
+    /// log frequencies of both halves less a penalty per edit. Empty if
+    /// `word` is in the dictionary or has no such split.
+    #[must_use]
+    pub fn runon_segmentations(&self, word: &str, n: usize) -> Vec<Segmentation> {
+        let word = self.normalize_query(word);
+        let word = word.as_ref();
+        if self.dictionary.contains(word) {
+            return Vec::new();
+        }
+        let mut segmentations: Vec<Segmentation> = self
+            .runon_splits(word)
+            .map(|(split, left, right, edits)| Segmentation {
+                segmented: format!("{} {}", &word[..split], &word[split..]),
+                score: self.split_score(&left, &right) - BEAM_EDIT_PENALTY * edits as f64,
+                corrected: format!("{left} {right}"),
+                distance: edits,
+            })
+            .collect();
+        segmentations.sort_by(|a, b| {
+            b.score
+                .total_cmp(&a.score)
+                .then_with(|| a.segmented.cmp(&b.segmented))
+        });
+        segmentations.truncate(n);
+        segmentations
+    }
+
+    // Each split point of `word` whose halves are words, or where one half is
+    // and the other can be corrected, as (split, left, right, edits).
+    fn runon_splits<'a>(
+        &'a self,
+        word: &'a str,
+    ) -> impl Iterator<Item = (usize, String, String, usize)> + 'a {
+        word.char_indices().skip(1).filter_map(move |(split, _)| {
+            let (left, right) = word.split_at(split);
+            let (left, right, edits) = match (
+                self.dictionary.contains(left),
+                self.dictionary.contains(right),
+            ) {
+                (true, true) => Some((left.to_string(), right.to_string(), 0)),
+                (true, false) => self
+                    .runon_correction(right)
+                    .map(|s| (left.to_string(), s.word, s.distance)),
+                (false, true) => self
+                    .runon_correction(left)
+                    .map(|s| (s.word, right.to_string(), s.distance)),
+                (false, false) => None,
+            }?;
+            Some((split, left, right, edits))
+        })
+    }
+
+    fn split_score(&self, left: &str, right: &str) -> f64 {
+        (1.0 + self.frequency_of(left) as f64).ln() + (1.0 + self.frequency_of(right) as f64).ln()
+    }
+
+    fn override_suggestion(&self, word: &str, target: &str) -> Suggestion {
+        let max_dist = word.len().max(target.len());
+        Suggestion {
+            word: target.to_string(),
+            distance: self.distance_within(word, target, max_dist),
+            frequency: self.frequency_of(target),
+            metadata: self.word_metadata.get(target).cloned(),
+        }
+    }
+
+    // Short halves are not corrected: almost any one or two letters are
+    // within a couple of edits of some word.
+    fn runon_correction(&self, half: &str) -> Option<Suggestion> {
+        if half.chars().count() <= self.max_edit_distance {
+            return None;
+        }
+        self.best_correction(half)
+    }
+
+    // Corpus count of a dictionary word, found through the deletion index
+    // since every word is its own deletion key.
+    fn frequency_of(&self, word: &str) -> u64 {
+        self.dictionary
+            .deletion_candidates(word)
+            .iter()
+            .find(|&&index| self.word_at(index) == word)
+            .map_or(0, |&index| self.dictionary.frequency(index))
+    }
+
+    /// Like `suggest_single_word_corrections`, but when `sentence_initial` is
+    /// set the first letter of every suggestion is capitalized, since the
+    /// dictionary usually stores words lowercase.
+    pub fn suggest_with_capitalization_rules(
+        &self,
+        word: &str,
+        n_suggestions: usize,
+        sentence_initial: bool,
+    ) -> SuggestedCorrection {
+        match self.suggest_single_word_corrections(word, n_suggestions) {
+            SuggestedCorrection::Suggestions(mut list) if sentence_initial => {
+                for suggestion in &mut list {
+                    suggestion.word = capitalize_first(&suggestion.word);
+                }
+                SuggestedCorrection::Suggestions(list)
+            }
+            other => other,
+        }
+    }
+
+    /// Measures how well this dictionary corrects labeled `(misspelling,
+    /// correct)` pairs, counting top-1 and top-`k` hits.
+    ///
+    /// A "misspelling" that is itself a dictionary word counts as a hit only
+    /// when it equals the expected word.
+    #[must_use]
+    pub fn evaluate(&self, pairs: &[(String, String)], k: usize) -> EvaluationReport {
+        let mut report = EvaluationReport {
+            total: pairs.len(),
+            k,
+            top1_hits: 0,
+            topk_hits: 0,
+        };
+        for (misspelling, correct) in pairs {
+            let ranked: Vec<String> = match self.suggest_single_word_corrections(misspelling, k) {
+                SuggestedCorrection::NoSuggestions => vec![misspelling.clone()],
+                SuggestedCorrection::Suggestions(list) => {
+                    list.into_iter().map(|s| s.word).collect()
+                }
+            };
+            if ranked.first() == Some(correct) {
+                report.top1_hits += 1;
+            }
+            if ranked.iter().take(k).any(|w| w == correct) {
+                report.topk_hits += 1;
+            }
+        }
+        report
+    }
+
+    /// Returns the words not in the dictionary, in input order, without
+    /// computing any suggestions.
+    #[must_use]
+    pub fn find_unknown_words(&self, words: &[String]) -> Vec<String> {
+        words
+            .par_iter()
+            .with_min_len(self.min_batch_len)
+            .filter(|word| !self.contains(word))
+            .cloned()
+            .collect()
+    }
+
+    /// Whether every word is in the dictionary.
+    pub fn contains_all(&self, words: &[String]) -> bool {
+        words
+            .par_iter()
+            .with_min_len(self.min_batch_len)
+            .all(|word| self.contains(word))
+    }
+
+    /// Replaces each misspelled word in `text` with its best suggestion.
+    ///
+    /// Leading and trailing punctuation of each token is preserved, and words
+    /// are looked up lowercased. Corrections at the start of a sentence are
+    /// capitalized. Misspelled words without any suggestion are handled
+    /// according to the corrector's `UnknownPolicy`. Whitespace between tokens
+    /// is collapsed to single spaces or kept verbatim depending on the
+    /// corrector's `WhitespaceMode`.
+    #[must_use]
+    pub fn correct_text(&self, text: &str) -> String {
+        self.correct_text_cow(text).into_owned()
+    }
+
+    /// Like `correct_text`, but returns `text` itself, borrowed, when
+    /// correcting it changes nothing, so mostly-correct input is passed
+    /// through without allocating.
+    #[must_use]
+    pub fn correct_text_cow<'a>(&self, text: &'a str) -> Cow<'a, str> {
+        self.correct_text_continuing(text, &mut true)
+    }
+
+    /// Corrects `reader` line by line into `writer`, like `correct_text` on
+    /// each line but keeping line breaks and tracking sentence starts across
+    /// lines. Only one line is held in memory at a time.
+    pub fn correct_stream<R: BufRead, W: Write>(
+        &self,
+        mut reader: R,
+        mut writer: W,
+    ) -> io::Result<()> {
+        let mut line = String::new();
+        let mut sentence_initial = true;
+        while reader.read_line(&mut line)? > 0 {
+            let content = line.trim_end_matches(['\n', '\r']);
+            let line_ending = &line[content.len()..];
+            writer.write_all(
+                self.correct_text_continuing(content, &mut sentence_initial)
+                    .as_bytes(),
+            )?;
+            writer.write_all(line_ending.as_bytes())?;
+            line.clear();
+        }
+        writer.flush()
+    }
+
+    /// Corrects the file at `input_path` into `output_path`, see
+    /// `correct_stream`.
+    pub fn correct_file(&self, input_path: &str, output_path: &str) -> io::Result<()> {
+        let reader = BufReader::new(File::open(input_path)?);
+        let writer = BufWriter::new(File::create(output_path)?);
+        self.correct_stream(reader, writer)
+    }
+
+    /// Like `correct_text`, also listing every word it changed, in order,
+    /// with its byte span in `text`, so editors can track, audit or undo the
+    /// changes. Spans refer to the input; with `WhitespaceMode::Collapse`
+    /// the corrected text may be laid out differently.
+    #[must_use]
+    pub fn correct_text_with_report(&self, text: &str) -> (String, Vec<Correction>) {
+        let mut changes = Vec::new();
+        let corrected = self.rebuild_text(text, &mut true, |token, start, sentence_initial| {
+            self.correct_token(token, start, sentence_initial, Some(&mut changes))
+        });
+        (corrected.into_owned(), changes)
+    }
+
+    // `correct_text` for a piece of a longer text; `sentence_initial` carries
+    // whether the next word starts a sentence from one piece to the next.
+    fn correct_text_continuing<'a>(
+        &self,
+        text: &'a str,
+        sentence_initial: &mut bool,
+    ) -> Cow<'a, str> {
+        self.rebuild_text(text, sentence_initial, |token, start, sentence_initial| {
+            self.correct_token(token, start, sentence_initial, None)
+        })
+    }
+
+    // Rebuilds `text` with each whitespace-free token replaced by
+    // `replace(token, start, sentence_initial)`, where `start` is the token's
+    // byte offset in `text`, dropping tokens it returns `None`
+    // for and handling whitespace according to the `WhitespaceMode`. The
+    // result borrows from `text` unless something actually changed.
+    fn rebuild_text<'a, F>(
+        &self,
+        text: &'a str,
+        sentence_initial: &mut bool,
+        mut replace: F,
+    ) -> Cow<'a, str>
+    where
+        F: FnMut(&'a str, usize, &mut bool) -> Option<Cow<'a, str>>,
+    {
+        let preserve = self.whitespace_mode == WhitespaceMode::Preserve;
+        let mut corrected = LazyText::new(text);
+        let mut pending_whitespace: Option<&str> = None;
+        let mut seen_token = false;
+        for token in tokenize(text) {
+            if token.is_whitespace {
+                // whitespace before a removed token is replaced by the run after it
+                pending_whitespace = Some(token.text);
+                continue;
+            }
+            let first_token = !seen_token;
+            seen_token = true;
+            let Some(replacement) = replace(token.text, token.start, sentence_initial) else {
+                continue;
+            };
+            if let Some(whitespace) = pending_whitespace.take() {
+                if preserve && (first_token || !corrected.is_empty()) {
+                    corrected.push_str(whitespace);
+                } else if !corrected.is_empty() {
+                    corrected.push_str(" ");
+                }
+            }
+            corrected.push_str(&replacement);
+        }
+        if preserve && let Some(whitespace) = pending_whitespace {
+            corrected.push_str(whitespace);
+        }
+        corrected.finish()
+    }
+
+    /// Like `correct_text`, but chooses corrections for the whole text at once
+    /// with a beam search instead of taking each word's best suggestion in
+    /// isolation.
+    ///
+    /// Each word contributes its frequency and an edit-distance penalty, and
+    /// each pair of adjacent words the count given to `with_bigram_frequencies`,
+    /// so "nw yrk" can become "new york" even when "now" is the more frequent
+    /// fix for "nw" on its own. The `beam_width` best partial corrections are
+    /// kept after each word, and each misspelled word contributes its
+    /// `beam_width` best suggestions, so a word further away can win if it is
+    /// much more frequent.
+    #[must_use]
+    pub fn correct_text_beam(&self, text: &str, beam_width: usize) -> String {
+        let beam_width = beam_width.max(1);
+        let words: Vec<Vec<BeamChoice>> = tokenize(text)
+            .into_iter()
+            .filter(|token| !token.is_whitespace)
+            .map(|token| split_punctuation_with_contractions(token.text, &self.contractions).1)
+            .filter(|word| !word.is_empty())
+            .map(|word| self.beam_choices(word, beam_width))
+            .collect();
+
+        // (score, choice index per word so far)
+        let mut beam: Vec<(f64, Vec<usize>)> = vec![(0.0, Vec::new())];
+        for (position, choices) in words.iter().enumerate() {
+            let mut next: Vec<(f64, Vec<usize>)> = Vec::with_capacity(beam.len() * choices.len());
+            for (score, path) in &beam {
+                let previous = path
+                    .last()
+                    .map(|&choice| words[position - 1][choice].context.as_str());
+                for (i, choice) in choices.iter().enumerate() {
+                    let bigram = previous
+                        .and_then(|prev| {
+                            self.bigram_frequencies
+                                .get(&(prev.to_string(), choice.context.clone()))
+                        })
+                        .copied()
+                        .unwrap_or(0);
+                    let mut path = path.clone();
+                    path.push(i);
+                    next.push((score + choice.score + (1.0 + bigram as f64).ln(), path));
+                }
+            }
+            // stable, so ties keep the order suggestions were ranked in
+            next.sort_by(|a, b| b.0.total_cmp(&a.0));
+            next.truncate(beam_width);
+            beam = next;
+        }
+        let best = beam.swap_remove(0).1;
+
+        let mut chosen = best.into_iter().zip(&words).map(|(i, choices)| &choices[i]);
+        self.rebuild_text(text, &mut true, |token, _, sentence_initial| {
+            let (prefix, word, suffix) =
+                split_punctuation_with_contractions(token, &self.contractions);
+            if word.is_empty() {
+                return Some(Cow::Borrowed(token));
+            }
+            let at_sentence_start = *sentence_initial;
+            *sentence_initial = suffix.contains(['.', '!', '?']);
+            let choice = chosen.next()?;
+            let text = choice.text.as_ref()?;
+            let text = if choice.corrected && at_sentence_start {
+                capitalize_first(text)
+            } else {
+                text.clone()
+            };
+            Some(Cow::Owned(format!("{}{}{}", prefix, text, suffix)))
+        })
+        .into_owned()
+    }
+
+    /// Replaces the contractions `correct_text` keeps whole instead of
+    /// stripping their apostrophes, by default `DEFAULT_CONTRACTIONS`. Without
+    /// this "'tis" would be looked up as "tis" and "corrected" to "this".
+    /// Matching ignores case and treats curly apostrophes as straight ones.
+    /// Contractions are always left as written.
+    pub fn with_contractions<I, S>(mut self, contractions: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        self.contractions = contractions
+            .into_iter()
+            .map(|c| c.as_ref().to_lowercase().replace('\u{2019}', "'"))
+            .collect();
+        self
+    }
+
+    /// Makes `correct_text` and `correct_text_beam` leave numbers alone,
+    /// including percentages and numbers with a unit from `DEFAULT_UNITS`,
+    /// e.g. "3.14", "20%" and "50km". Off by default, when "50km" may be
+    /// "corrected" to a nearby word.
+    pub fn with_numeric_tokens(mut self, enabled: bool) -> Self {
+        self.numeric_units = enabled.then(|| DEFAULT_UNITS.iter().map(|u| u.to_string()).collect());
+        self
+    }
+
+    /// Like `with_numeric_tokens(true)`, but with `units` as the recognized
+    /// unit suffixes (matched ignoring case).
+    pub fn with_numeric_units<I, S>(mut self, units: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let units = units.into_iter().map(|u| u.as_ref().to_lowercase());
+        self.numeric_units = Some(units.collect());
+        self
+    }
+
+    /// Makes `correct_text` and `correct_text_beam` leave all-caps words of
+    /// at least `min_len` letters as they are, taking them for acronyms like
+    /// "NASA" or "HTTP", while shorter ones like "TEH" are still corrected.
+    /// 0, the default, treats all-caps words like any other.
+    pub fn with_acronym_min_len(mut self, min_len: usize) -> Self {
+        self.acronym_min_len = min_len;
+        self
+    }
+
+    /// Makes `correct_text` treat words in camelCase, PascalCase or
+    /// snake_case as identifiers and correct them with `correct_identifier`
+    /// instead of as one word. Off by default.
+    pub fn with_identifier_splitting(mut self, enabled: bool) -> Self {
+        self.identifier_splitting = enabled;
+        self
+    }
+
+    /// Corrects each subword of a code identifier (see
+    /// `tokenize::split_identifier`) and reassembles it in the original
+    /// convention: "getUsrName" becomes "getUserName" and "usr_count"
+    /// becomes "user_count". A corrected subword takes the casing of the one
+    /// it replaces (lowercase, Capitalized or ALL CAPS); subwords that are
+    /// correct, too short to correct reliably (no longer than
+    /// `max_edit_distance`) or have no suggestion are kept.
+    #[must_use]
+    pub fn correct_identifier(&self, identifier: &str) -> String {
+        split_identifier(identifier)
+            .into_iter()
+            .map(|piece| {
+                if !piece.chars().all(char::is_alphabetic)
+                    || piece.chars().count() <= self.max_edit_distance
+                {
+                    return Cow::Borrowed(piece);
+                }
+                let lowercase = piece.to_lowercase();
+                let Some(best) = self
+                    .best_correction(&lowercase)
+                    .filter(|best| self.passes_length_ratio_guard(piece, &best.word))
+                else {
+                    return Cow::Borrowed(piece);
+                };
+                let mut chars = piece.chars();
+                let first_upper = chars.next().is_some_and(char::is_uppercase);
+                Cow::Owned(if first_upper && chars.all(char::is_uppercase) {
+                    best.word.to_uppercase()
+                } else if first_upper {
+                    capitalize_first(&best.word)
+                } else {
+                    best.word
+                })
+            })
+            .collect()
+    }
+
+    // A word with an inner underscore or a lowercase-to-uppercase step.
+    fn is_identifier(&self, word: &str) -> bool {
+        self.identifier_splitting && split_identifier(word).len() > 1
+    }
+
+    fn is_acronym(&self, word: &str) -> bool {
+        if self.acronym_min_len == 0 {
+            return false;
+        }
+        let letters = word.chars().filter(|c| c.is_alphabetic()).count();
+        letters >= self.acronym_min_len && !word.chars().any(char::is_lowercase)
+    }
+
+    fn is_number(&self, word: &str) -> bool {
+        self.numeric_units
+            .as_ref()
+            .is_some_and(|units| is_numeric_token(word, units))
+    }
+
+    // Whether a word from `split_punctuation_with_contractions` kept its
+    // apostrophes, which only happens for a known contraction.
+    fn is_contraction(&self, word: &str) -> bool {
+        word.starts_with(['\'', '\u{2019}']) || word.ends_with(['\'', '\u{2019}'])
+    }
+
+    /// Sets counts of adjacent word pairs, e.g. from a corpus, used by
+    /// `correct_text_beam` to prefer corrections that fit their neighbours.
+    pub fn with_bigram_frequencies(mut self, bigrams: HashMap<(String, String), u64>) -> Self {
+        self.bigram_frequencies = bigrams;
+        self
+    }
+
+    // The options `correct_text_beam` weighs for one word: the word itself if
+    // it is correct or cannot be fixed, otherwise its best suggestions.
+    fn beam_choices(&self, word: &str, beam_width: usize) -> Vec<BeamChoice> {
+        let lowercase = word.to_lowercase();
+        let keep = self.contains(&lowercase)
+            || self.is_contraction(word)
+            || self.is_number(word)
+            || self.is_acronym(word);
+        let suggestions = if keep {
+            Vec::new()
+        } else {
+            match self.suggest_single_word_corrections(&lowercase, beam_width) {
+                SuggestedCorrection::Suggestions(list) => list
+                    .into_iter()
+                    .filter(|s| self.passes_length_ratio_guard(word, &s.word))
+                    .collect(),
+                SuggestedCorrection::NoSuggestions => Vec::new(),
+            }
+        };
+        if suggestions.is_empty() {
+            let text = if keep {
+                Some(word.to_string())
+            } else {
+                match &self.unknown_policy {
+                    UnknownPolicy::Leave => Some(word.to_string()),
+                    UnknownPolicy::Mark(marker) => Some(marker.clone()),
+                    UnknownPolicy::Remove => None,
+                }
+            };
+            return vec![BeamChoice {
+                text,
+                context: lowercase,
+                corrected: false,
+                score: 0.0,
+            }];
+        }
+        suggestions
+            .into_iter()
+            .map(|s| BeamChoice {
+                score: (1.0 + s.frequency as f64).ln() - BEAM_EDIT_PENALTY * s.distance as f64,
+                text: Some(s.word.clone()),
+                context: s.word,
+                corrected: true,
+            })
+            .collect()
+    }
+
+    // Corrects one whitespace-free token starting at byte `start`, recording
+    // the change in `changes` if given, or returns `None` if the
+    // `UnknownPolicy` removes it.
+    fn correct_token<'a>(
+        &self,
+        token: &'a str,
+        start: usize,
+        sentence_initial: &mut bool,
+        changes: Option<&mut Vec<Correction>>,
+    ) -> Option<Cow<'a, str>> {
+        let (prefix, word, suffix) = split_punctuation_with_contractions(token, &self.contractions);
+        let record = |replacement: &str, distance: Option<usize>| {
+            if let Some(changes) = changes {
+                let word_start = start + prefix.len();
+                changes.push(Correction {
+                    span: word_start..word_start + word.len(),
+                    original: word.to_string(),
+                    replacement: replacement.to_string(),
+                    distance,
+                });
+            }
+        };
+        if word.is_empty() {
+            return Some(Cow::Borrowed(token));
+        }
+        let at_sentence_start = *sentence_initial;
+        *sentence_initial = suffix.contains(['.', '!', '?']);
+        if self.is_contraction(word) || self.is_number(word) || self.is_acronym(word) {
+            return Some(Cow::Borrowed(token));
+        }
+        if self.is_identifier(word) {
+            let corrected = self.correct_identifier(word);
+            if corrected == word {
+                return Some(Cow::Borrowed(token));
+            }
+            let (from, to) = (word.to_lowercase(), corrected.to_lowercase());
+            record(
+                &corrected,
+                Some(self.distance_within(&from, &to, from.len().max(to.len()))),
+            );
+            return Some(Cow::Owned(format!("{}{}{}", prefix, corrected, suffix)));
+        }
+        if self.real_word_mode && self.contains(&word.to_lowercase()) {
+            return Some(Cow::Borrowed(token));
+        }
+
+        let (replacement, distance) = match self.suggest_with_capitalization_rules(
+            &word.to_lowercase(),
+            1,
+            at_sentence_start,
+        ) {
+            SuggestedCorrection::NoSuggestions => return Some(Cow::Borrowed(token)),
+            SuggestedCorrection::Suggestions(list) => match list.into_iter().next() {
+                Some(best) if !self.passes_length_ratio_guard(word, &best.word) => {
+                    return Some(Cow::Borrowed(token));
+                }
+                Some(best) => (best.word, Some(best.distance)),
+                None => match &self.unknown_policy {
+                    UnknownPolicy::Leave => return Some(Cow::Borrowed(token)),
+                    UnknownPolicy::Mark(marker) => (marker.clone(), None),
+                    UnknownPolicy::Remove => {
+                        record("", None);
+                        return None;
+                    }
+                },
+            },
+        };
+        record(&replacement, distance);
+        Some(Cow::Owned(format!("{}{}{}", prefix, replacement, suffix)))
+    }
+}
+
+// Text being rebuilt by `rebuild_text`. It stays a borrow of `source` for as
+// long as everything pushed matches the source, and only copies into an
+// owned buffer at the first difference.
+struct LazyText<'a> {
+    source: &'a str,
+    borrowed: usize, // while `owned` is None, the text so far is source[..borrowed]
+    owned: Option<String>,
+}
+
+impl<'a> LazyText<'a> {
+    fn new(source: &'a str) -> Self {
+        LazyText {
+            source,
+            borrowed: 0,
+            owned: None,
+        }
+    }
+
+    fn is_empty(&self) -> bool {
+        match &self.owned {
+            Some(owned) => owned.is_empty(),
+            None => self.borrowed == 0,
+        }
+    }
+
+    fn push_str(&mut self, piece: &str) {
+        if let Some(owned) = &mut self.owned {
+            owned.push_str(piece);
+        } else if self.source[self.borrowed..].starts_with(piece) {
+            self.borrowed += piece.len();
+        } else {
+            let mut owned = String::with_capacity(self.source.len());
+            owned.push_str(&self.source[..self.borrowed]);
+            owned.push_str(piece);
+            self.owned = Some(owned);
+        }
+    }
+
+    fn finish(self) -> Cow<'a, str> {
+        match self.owned {
+            Some(owned) => Cow::Owned(owned),
+            None => Cow::Borrowed(&self.source[..self.borrowed]),
+        }
+    }
+}
+
+/// Builds a `SpellCorrector` from words that arrive one at a time, e.g. from
+/// a network stream or a generator, indexing each as it is pushed instead of
+/// collecting the whole list first.
+///
+/// Pushing a word that is already present adds to its count, like
+/// `add_word_to_dictionary_with_frequency`.
+pub struct SpellCorrectorBuilder {
+    dictionary: InMemoryDictionary,
+    max_edit_distance: usize,
+}
+
+impl SpellCorrectorBuilder {
+    pub fn new(max_edit_distance: usize) -> Self {
+        SpellCorrectorBuilder {
+            dictionary: InMemoryDictionary::default(),
+            max_edit_distance,
+        }
+    }
+
+    pub fn push(&mut self, word: impl Into<String>) {
+        self.push_with_frequency(word, 0);
+    }
+
+    pub fn push_with_frequency(&mut self, word: impl Into<String>, frequency: u64) {
+        self.dictionary
+            .push(word.into(), frequency, self.max_edit_distance);
+    }
+
+    /// Number of distinct words pushed so far.
+    pub fn len(&self) -> usize {
+        self.dictionary.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.dictionary.is_empty()
+    }
+
+    pub fn finish(self) -> SpellCorrector {
+        SpellCorrector::from_dictionary(self.dictionary, self.max_edit_distance)
+    }
+}
+
+impl<D: Dictionary + Clone> Clone for SpellCorrector<D> {
+    /// Deep-copies the dictionary and deletion index. The clone starts with
+    /// empty caches of its own, so it can be handed to another thread without
+    /// sharing cache state with the original.
+    fn clone(&self) -> Self {
+        let mut corrector = Self::from_dictionary(self.dictionary.clone(), self.max_edit_distance);
+        corrector.word_max_edit_distances = self.word_max_edit_distances.clone();
+        corrector.distance_fn = self.distance_fn.clone();
+        corrector.deletion_depth = self.deletion_depth.clone();
+        corrector.confusion_set = self.confusion_set.clone();
+        corrector.case_insensitive = self.case_insensitive;
+        corrector.invisible_chars = self.invisible_chars.clone();
+        corrector.normalized_ranking = self.normalized_ranking;
+        corrector.error_model = self.error_model.clone();
+        corrector.pinned = self.pinned.clone();
+        corrector.lcs_tie_break = self.lcs_tie_break;
+        corrector.phonetic_tie_break = self.phonetic_tie_break;
+        corrector.position_weighted_ranking = self.position_weighted_ranking;
+        corrector.wildcards = self.wildcards;
+        corrector.contractions = self.contractions.clone();
+        corrector.numeric_units = self.numeric_units.clone();
+        corrector.acronym_min_len = self.acronym_min_len;
+        corrector.identifier_splitting = self.identifier_splitting;
+        corrector.correction_overrides = self.correction_overrides.clone();
+        corrector.expansions = self.expansions.clone();
+        corrector.acceptances = self.acceptances.clone();
+        corrector.fold_diacritics = self.fold_diacritics;
+        corrector.fold_hyphens = self.fold_hyphens;
+        corrector.folded_keys = self.folded_keys.clone();
+        corrector.anagram_keys = self.anagram_keys.clone();
+        corrector.real_word_mode = self.real_word_mode;
+        corrector.space_edits = self.space_edits;
+        corrector.pad_suggestions = self.pad_suggestions;
+        corrector.exclude_input = self.exclude_input;
+        corrector.length_ratio_band = self.length_ratio_band;
+        corrector.max_suggestion_len = self.max_suggestion_len;
+        corrector.min_frequency = self.min_frequency;
+        corrector.word_metadata = self.word_metadata.clone();
+        corrector.min_batch_len = self.min_batch_len;
+        corrector.parallel_threshold = self.parallel_threshold;
+        corrector.bigram_frequencies = self.bigram_frequencies.clone();
+        #[cfg(feature = "logging")]
+        {
+            corrector.slow_query_candidates = self.slow_query_candidates;
+        }
+        corrector
+            .with_negative_cache_capacity(self.negative_cache_capacity)
+            .with_per_thread_cache(self.thread_caches.is_some())
+            .with_unknown_policy(self.unknown_policy.clone())
+            .with_whitespace_mode(self.whitespace_mode)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    #[test]
+    fn test_bounded_levenshtein() {
+        assert_eq!(bounded_levenshtein("kitten", "sitting", 3), 3);
+        assert_eq!(bounded_levenshtein("flaw", "lawn", 2), 2);
+        assert_eq!(bounded_levenshtein("intention", "execution", 5), 5);
+    }
+
+    #[test]
+    fn test_deletion_variants() {
+        let variants = deletion_variants("spelling", 2, false);
+        assert!(variants.contains("speling"));
+        assert!(variants.contains("speling"));
+        assert!(!variants.contains("spelling"));
+    }
+
+    #[test]
+    fn test_suggest_single_word_corrections() {
+        let dictionary: Vec<String> = vec!["spelling".to_string(), "corrected".to_string()];
+
+        let spell_corrector = SpellCorrector::new(dictionary, 2);
+        let suggestions = spell_corrector.suggest_single_word_corrections("speling", 2);
+
+        match suggestions {
+            SuggestedCorrection::NoSuggestions => panic!("Expected suggestions, but got none."),
+            SuggestedCorrection::Suggestions(suggestions) => {
+                assert_eq!(suggestions.len(), 1);
+                assert_eq!(suggestions[0].word, "spelling");
+            }
+        }
+    }
+
+    #[test]
+    fn test_bounded_levenshtein_identical() {
+        assert_eq!(bounded_levenshtein("same", "same", 0), 0);
+    }
+
+    #[test]
+    fn test_bounded_levenshtein_cutoff() {
+        // real distance = 3, bound = 2  ⇒  function must bail out (> bound)
+        assert!(bounded_levenshtein("kitten", "sitting", 2) > 2);
+    }
+
+    #[test]
+    fn test_deletion_variants_zero() {
+        // With max_del = 0 we expect *no* variants
+        assert!(deletion_variants("abc", 0, false).is_empty());
+    }
+
+    #[test]
+    fn test_deletion_variants_exhaustive() {
+        let v = deletion_variants("abc", 2, false);
+        // All unique strings reachable by 1 or 2 deletions
+        for s in ["ab", "ac", "bc", "a", "b", "c"] {
+            assert!(v.contains(s), "missing variant {}", s);
+        }
+        // Original word must be absent
+        assert!(!v.contains("abc"));
+    }
+
+    #[test]
     fn test_suggest_multiple_corrections_order() {
         let dict: Vec<String> = ["spelling", "spilling", "selling"]
             .iter()
@@ -313,42 +3667,2687 @@ mod tests {
             .collect();
         let corrector = SpellCorrector::new(dict, 2);
 
-        println!(
-            "Res: {:?}",
-            corrector.suggest_single_word_corrections("speling", 2)
+        println!(
+            "Res: {:?}",
+            corrector.suggest_single_word_corrections("speling", 2)
+        );
+
+        match corrector.suggest_single_word_corrections("speling", 2) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list[0].word, "spelling"); // distance 1
+                assert_eq!(list[1].word, "selling"); // distance 2, same length as the query
+                assert_eq!(list.len(), 2);
+            }
+            _ => panic!("expected suggestions"),
+        }
+    }
+
+    #[test]
+    fn test_add_word_updates_dictionary() {
+        let dict: Vec<String> = ["cat"].iter().map(|s| s.to_string()).collect();
+        let mut corrector = SpellCorrector::new(dict, 1);
+
+        println!(
+            "Res: {:?}",
+            corrector.suggest_single_word_corrections("cart", 2)
+        );
+        // "cart" is unknown → only "cat" suggested
+        if let SuggestedCorrection::Suggestions(list) =
+            corrector.suggest_single_word_corrections("cart", 2)
+        {
+            assert_eq!(list[0].word, "cat");
+        }
+
+        // Add "cart", run again – now no suggestions (exact hit)
+        corrector.add_word_to_dictionary("cart");
+        match corrector.suggest_single_word_corrections("cart", 2) {
+            SuggestedCorrection::NoSuggestions => {}
+            _ => panic!("expected no suggestions after adding exact word"),
+        }
+    }
+
+    #[test]
+    fn test_negative_cache_hit() {
+        let dict: Vec<String> = ["spelling"].iter().map(|s| s.to_string()).collect();
+        let mut corrector = SpellCorrector::new(dict, 1).with_negative_cache(true);
+
+        match corrector.suggest_single_word_corrections("xyzzy", 3) {
+            SuggestedCorrection::Suggestions(list) => assert!(list.is_empty()),
+            _ => panic!("expected an empty suggestion list"),
+        }
+        assert!(
+            corrector
+                .negative_cache
+                .as_ref()
+                .unwrap()
+                .get(&"xyzzy".to_string())
+                .is_some()
+        );
+
+        // Sneak a matching word into the index without invalidating caches:
+        // a second query is only still empty if it was answered from the
+        // negative cache rather than a fresh candidate scan.
+        corrector.dictionary.push("xyzzyy".to_string(), 0, 1);
+        match corrector.suggest_single_word_corrections("xyzzy", 3) {
+            SuggestedCorrection::Suggestions(list) => assert!(list.is_empty()),
+            _ => panic!("expected an empty suggestion list"),
+        }
+
+        // Adding a word through the public API invalidates the negative cache.
+        corrector.add_word_to_dictionary("xyzzz");
+        match corrector.suggest_single_word_corrections("xyzzy", 3) {
+            SuggestedCorrection::Suggestions(list) => assert!(!list.is_empty()),
+            _ => panic!("expected suggestions after adding a nearby word"),
+        }
+    }
+
+    #[test]
+    fn test_clone_matches_original() {
+        let dict: Vec<String> = ["spelling", "spilling", "selling", "corrected"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let original = SpellCorrector::new(dict, 2);
+        // Warm the original's cache; the clone must not depend on it.
+        let _ = original.suggest_single_word_corrections("speling", 3);
+        let copy = original.clone();
+
+        for query in ["speling", "corected", "seling", "spelling"] {
+            let expected = original.suggest_single_word_corrections(query, 3);
+            let actual = copy.suggest_single_word_corrections(query, 3);
+            match (expected, actual) {
+                (SuggestedCorrection::NoSuggestions, SuggestedCorrection::NoSuggestions) => {}
+                (SuggestedCorrection::Suggestions(a), SuggestedCorrection::Suggestions(b)) => {
+                    let a: Vec<_> = a.into_iter().map(|s| (s.word, s.distance)).collect();
+                    let b: Vec<_> = b.into_iter().map(|s| (s.word, s.distance)).collect();
+                    assert_eq!(a, b, "mismatch for {}", query);
+                }
+                _ => panic!("clone disagreed with original for {}", query),
+            }
+        }
+    }
+
+    #[test]
+    fn test_suggest_best_tier_keeps_all_ties() {
+        let dict: Vec<String> = ["cat", "bat", "hat", "rat", "cart", "chat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 2);
+
+        match corrector.suggest_best_tier("zat") {
+            SuggestedCorrection::Suggestions(list) => {
+                let words: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                assert_eq!(words, vec!["bat", "cat", "hat", "rat"]);
+                assert!(list.iter().all(|s| s.distance == 1));
+            }
+            _ => panic!("expected suggestions"),
+        }
+        assert!(matches!(
+            corrector.suggest_best_tier("cat"),
+            SuggestedCorrection::NoSuggestions
+        ));
+
+        // answered by the same checks as the plain query
+        let mut corrector = corrector.with_real_word_mode(true);
+        corrector.add_correction_override("zat", "chat");
+        let words_of = |word: &str| -> Vec<String> {
+            corrector
+                .suggest_best_tier(word)
+                .into_suggestions()
+                .into_iter()
+                .map(|s| s.word)
+                .collect()
+        };
+        assert_eq!(words_of("zat"), vec!["chat"]);
+        assert_eq!(words_of("cat"), vec!["bat", "hat", "rat", "cart", "chat"]);
+    }
+
+    #[test]
+    fn test_build_with_stats() {
+        let dict: Vec<String> = ["ab", "ac"].iter().map(|s| s.to_string()).collect();
+        let (corrector, stats) = SpellCorrector::build_with_stats(dict, 1);
+        assert_eq!(stats.words, 2);
+        // "ab", "a", "b", "ac", "c"; "a" is shared by both words
+        assert_eq!(stats.deletion_keys, 5);
+        assert_eq!(stats.entries, 6);
+        assert_eq!(
+            corrector.dictionary.dictionary_del_mappings.len(),
+            stats.deletion_keys
+        );
+    }
+
+    #[test]
+    fn test_trace_reports_linking_key() {
+        let dict: Vec<String> = ["spelling", "cat"].iter().map(|s| s.to_string()).collect();
+        let corrector = SpellCorrector::new(dict, 2);
+
+        let trace = corrector.trace("spelxing", 5);
+        assert!(!trace.in_dictionary);
+        assert_eq!(trace.suggestions.len(), 1);
+        assert_eq!(trace.suggestions[0].suggestion.word, "spelling");
+        assert_eq!(trace.suggestions[0].suggestion.distance, 1);
+        // Dropping the substituted character from each side yields "speling".
+        assert_eq!(trace.suggestions[0].matched_key, "speling");
+
+        assert!(corrector.trace("cat", 5).in_dictionary);
+    }
+
+    fn unknown_policy_corrector(policy: UnknownPolicy) -> SpellCorrector {
+        let dict: Vec<String> = ["the", "cat", "sat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        SpellCorrector::new(dict, 1).with_unknown_policy(policy)
+    }
+
+    #[test]
+    fn test_correct_text_unknown_leave() {
+        let corrector = unknown_policy_corrector(UnknownPolicy::Leave);
+        assert_eq!(
+            corrector.correct_text("the cst sat, qqqq!"),
+            "the cat sat, qqqq!"
+        );
+    }
+
+    #[test]
+    fn test_correct_text_unknown_mark() {
+        let corrector = unknown_policy_corrector(UnknownPolicy::Mark("[?]".to_string()));
+        assert_eq!(
+            corrector.correct_text("the cst sat, qqqq!"),
+            "the cat sat, [?]!"
+        );
+    }
+
+    #[test]
+    fn test_correct_text_unknown_remove() {
+        let corrector = unknown_policy_corrector(UnknownPolicy::Remove);
+        assert_eq!(corrector.correct_text("the qqqq cst sat"), "the cat sat");
+    }
+
+    #[test]
+    fn test_add_word_with_frequency_outranks() {
+        let corrector_words = vec![("bat".to_string(), 50), ("cat".to_string(), 100)];
+        let mut corrector = SpellCorrector::new_with_frequencies(corrector_words, 1);
+
+        match corrector.suggest_single_word_corrections("zat", 3) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list[0].word, "cat");
+                assert_eq!(list[0].frequency, 100);
+            }
+            _ => panic!("expected suggestions"),
+        }
+
+        corrector.add_word_to_dictionary_with_frequency("hat", 1000);
+        match corrector.suggest_single_word_corrections("zat", 3) {
+            SuggestedCorrection::Suggestions(list) => {
+                let words: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                assert_eq!(words, vec!["hat", "cat", "bat"]);
+            }
+            _ => panic!("expected suggestions"),
+        }
+    }
+
+    #[test]
+    fn test_word_max_edit_distance_override() {
+        let dict: Vec<String> = ["to", "toe", "tow"].iter().map(|s| s.to_string()).collect();
+        let mut corrector = SpellCorrector::new(dict, 2);
+        corrector.set_word_max_edit_distance("to", 0);
+        corrector.set_word_max_edit_distance("toe", 1);
+
+        // "to" is never suggested, "toe" only one edit away, "tow" as usual.
+        match corrector.suggest_single_word_corrections("tox", 5) {
+            SuggestedCorrection::Suggestions(list) => {
+                let words: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                assert_eq!(words, vec!["toe", "tow"]);
+            }
+            _ => panic!("expected suggestions"),
+        }
+        match corrector.suggest_single_word_corrections("txx", 5) {
+            SuggestedCorrection::Suggestions(list) => {
+                let words: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                assert_eq!(words, vec!["tow"]);
+            }
+            _ => panic!("expected suggestions"),
+        }
+        // Exact hits are unaffected by the override.
+        assert!(matches!(
+            corrector.suggest_single_word_corrections("to", 5),
+            SuggestedCorrection::NoSuggestions
+        ));
+    }
+
+    #[test]
+    fn test_capitalization_rules_sentence_initial() {
+        let dict: Vec<String> = ["the", "cat", "sat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 2);
+
+        match corrector.suggest_with_capitalization_rules("teh", 1, true) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "The"),
+            _ => panic!("expected suggestions"),
+        }
+        match corrector.suggest_with_capitalization_rules("teh", 1, false) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "the"),
+            _ => panic!("expected suggestions"),
+        }
+        assert_eq!(
+            corrector.correct_text("teh cat sat. teh cst sat"),
+            "The cat sat. The cat sat"
+        );
+    }
+
+    #[test]
+    fn test_custom_distance_fn() {
+        let dict: Vec<String> = ["cart", "cast"].iter().map(|s| s.to_string()).collect();
+
+        let corrector = SpellCorrector::new(dict.clone(), 1);
+        match corrector.suggest_single_word_corrections("cat", 2) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list[0].word, "cart");
+                assert_eq!(list[0].distance, 1);
+            }
+            _ => panic!("expected suggestions"),
+        }
+
+        let corrector = SpellCorrector::new(dict.clone(), 1).with_distance_fn(|_, _, _| 0);
+        match corrector.suggest_single_word_corrections("cat", 2) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list.len(), 2);
+                assert!(list.iter().all(|s| s.distance == 0));
+            }
+            _ => panic!("expected suggestions"),
+        }
+
+        let corrector = SpellCorrector::new(dict, 1)
+            .with_distance_fn(|_, candidate, _| if candidate == "cast" { 0 } else { 1 });
+        match corrector.suggest_single_word_corrections("cat", 2) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "cast"),
+            _ => panic!("expected suggestions"),
+        }
+    }
+
+    #[test]
+    fn test_evaluate_accuracy() {
+        let dict = vec![
+            ("spelling".to_string(), 10),
+            ("spilling".to_string(), 20),
+            ("house".to_string(), 5),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(dict, 2);
+        let pairs: Vec<(String, String)> = [
+            ("speling", "spelling"),  // "spelling" is closer: top-1 hit
+            ("spxlling", "spelling"), // ties with "spilling", which is more frequent
+            ("hous", "house"),
+            ("zzzz", "house"), // no suggestions at all
+        ]
+        .iter()
+        .map(|(a, b)| (a.to_string(), b.to_string()))
+        .collect();
+
+        let report = corrector.evaluate(&pairs, 2);
+        assert_eq!(report.total, 4);
+        assert_eq!(report.top1_hits, 2);
+        assert_eq!(report.topk_hits, 3);
+        assert_eq!(report.top1_accuracy(), 0.5);
+        assert_eq!(report.topk_accuracy(), 0.75);
+    }
+
+    #[test]
+    fn test_correct_text_preserves_whitespace() {
+        let dict: Vec<String> = ["the", "cat", "sat", "on", "mat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let text = "  the cst\n\tsat  on\n\nthe mst.\n";
+
+        let collapsing = SpellCorrector::new(dict.clone(), 1);
+        assert_eq!(collapsing.correct_text(text), "the cat sat on the mat.");
+
+        let preserving =
+            SpellCorrector::new(dict.clone(), 1).with_whitespace_mode(WhitespaceMode::Preserve);
+        assert_eq!(
+            preserving.correct_text(text),
+            "  the cat\n\tsat  on\n\nthe mat.\n"
+        );
+
+        let removing = SpellCorrector::new(dict, 1)
+            .with_whitespace_mode(WhitespaceMode::Preserve)
+            .with_unknown_policy(UnknownPolicy::Remove);
+        assert_eq!(removing.correct_text("the qqqq\ncat"), "the\ncat");
+    }
+
+    #[test]
+    fn test_for_each_suggestion_counts() {
+        let dict: Vec<String> = ["cat", "bat", "hat", "rat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1);
+
+        let mut seen = Vec::new();
+        corrector.for_each_suggestion("zat", 3, |s| seen.push(s.word.clone()));
+        assert_eq!(seen, vec!["bat", "cat", "hat"]);
+
+        let mut calls = 0;
+        corrector.for_each_suggestion("cat", 3, |_| calls += 1);
+        assert_eq!(calls, 0);
+    }
+
+    #[test]
+    fn test_from_frequency_map() {
+        let map: HashMap<String, u64> = [("bat", 5), ("cat", 50), ("hat", 5)]
+            .iter()
+            .map(|(w, c)| (w.to_string(), *c))
+            .collect();
+        let corrector = SpellCorrector::from_frequency_map(map, 1);
+
+        assert_eq!(corrector.dictionary.words, vec!["cat", "bat", "hat"]);
+        assert_eq!(corrector.dictionary.frequencies, vec![50, 5, 5]);
+        match corrector.suggest_single_word_corrections("zat", 3) {
+            SuggestedCorrection::Suggestions(list) => {
+                let words: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                assert_eq!(words, vec!["cat", "bat", "hat"]);
+            }
+            _ => panic!("expected suggestions"),
+        }
+    }
+
+    #[test]
+    fn test_weighted_edit_costs_change_ranking() {
+        let dict: Vec<String> = ["bac", "abd"].iter().map(|s| s.to_string()).collect();
+        let top = |corrector: &SpellCorrector| match corrector
+            .suggest_single_word_corrections("abc", 2)
+        {
+            SuggestedCorrection::Suggestions(list) => list[0].word.clone(),
+            _ => panic!("expected suggestions"),
+        };
+
+        let keyboard = SpellCorrector::new(dict.clone(), 2)
+            .with_weighted_edit_costs(WeightedEditCosts::keyboard());
+        assert_eq!(top(&keyboard), "bac");
+
+        let ocr = SpellCorrector::new(dict, 2).with_weighted_edit_costs(WeightedEditCosts::ocr());
+        assert_eq!(top(&ocr), "abd");
+    }
+
+    #[test]
+    fn test_find_unknown_words() {
+        let dict: Vec<String> = ["the", "cat", "sat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1);
+        let words: Vec<String> = ["the", "cst", "sat", "qqq", "cat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+
+        assert_eq!(corrector.find_unknown_words(&words), vec!["cst", "qqq"]);
+        assert!(!corrector.contains_all(&words));
+        assert!(corrector.contains_all(&words[..1]));
+    }
+
+    #[test]
+    fn test_confusion_set_expansion() {
+        let dict: Vec<String> = ["OCR", "modern"].iter().map(|s| s.to_string()).collect();
+        let confusions: HashMap<String, Vec<String>> = [
+            ("0".to_string(), vec!["O".to_string()]),
+            ("rn".to_string(), vec!["m".to_string()]),
+        ]
+        .into_iter()
+        .collect();
+
+        let plain = SpellCorrector::new(dict.clone(), 1);
+        match plain.suggest_single_word_corrections("rnodern", 1) {
+            SuggestedCorrection::Suggestions(list) => assert!(list.is_empty()),
+            _ => panic!("expected an empty suggestion list"),
+        }
+
+        let corrector = SpellCorrector::new(dict, 1).with_confusion_set(confusions);
+        match corrector.suggest_single_word_corrections("0CR", 1) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list[0].word, "OCR");
+                assert_eq!(list[0].distance, 0);
+            }
+            _ => panic!("expected suggestions"),
+        }
+        match corrector.suggest_single_word_corrections("rnodern", 1) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "modern"),
+            _ => panic!("expected suggestions"),
+        }
+    }
+
+    #[test]
+    fn test_trace_counts_candidates() {
+        let dict: Vec<String> = ["cat", "act", "bat", "dog", "elephant"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1);
+
+        // "cat" and "act" share the deletion variant "ct" with "cst", though
+        // "act" is two edits away; the other words are never looked at.
+        let trace = corrector.trace("cst", 5);
+        assert_eq!(trace.candidates_considered, 2);
+        assert_eq!(trace.suggestions.len(), 1);
+        assert_eq!(corrector.trace("cat", 5).candidates_considered, 0);
+    }
+
+    #[test]
+    fn test_case_sensitivity_modes() {
+        let dict: Vec<String> = ["hello", "World"].iter().map(|s| s.to_string()).collect();
+
+        // Case-sensitive by default: "Hello" is one substitution from "hello".
+        let sensitive = SpellCorrector::new(dict.clone(), 1);
+        assert!(!sensitive.contains("Hello"));
+        match sensitive.suggest_single_word_corrections("Hello", 1) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list[0].word, "hello");
+                assert_eq!(list[0].distance, 1);
+            }
+            _ => panic!("expected suggestions"),
+        }
+
+        let insensitive = SpellCorrector::new(dict, 1).with_case_insensitive(true);
+        assert!(insensitive.contains("Hello"));
+        assert!(matches!(
+            insensitive.suggest_single_word_corrections("Hello", 1),
+            SuggestedCorrection::NoSuggestions
+        ));
+        // Uppercase dictionary entries are lowercased and re-indexed.
+        assert!(matches!(
+            insensitive.suggest_single_word_corrections("WORLD", 1),
+            SuggestedCorrection::NoSuggestions
+        ));
+        match insensitive.suggest_single_word_corrections("Wrld", 1) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "world"),
+            _ => panic!("expected suggestions"),
+        }
+    }
+
+    #[test]
+    fn test_one_deletion_fast_path() {
+        let words = vec![
+            ("hello".to_string(), 10),
+            ("hell".to_string(), 5),
+            ("help".to_string(), 50),
+            ("halo".to_string(), 1),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 2);
+
+        // Deleting one "l" of "helllo" gives "hello", so the shortcut applies.
+        let fast = corrector.one_deletion_fast_path("helllo", 1).unwrap();
+        assert_eq!(fast.len(), 1);
+        assert_eq!(fast[0].word, "hello");
+        match corrector.suggest_single_word_corrections("helllo", 1) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list[0].word, "hello");
+                assert_eq!(list[0].distance, 1);
+            }
+            _ => panic!("expected suggestions"),
+        }
+
+        // Asking for more than the distance-1 matches falls back to the full
+        // scan, which also finds the farther "hell".
+        assert!(corrector.one_deletion_fast_path("helllo", 2).is_none());
+        match corrector.suggest_single_word_corrections("helllo", 2) {
+            SuggestedCorrection::Suggestions(list) => {
+                let words: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                assert_eq!(words, vec!["hello", "hell"]);
+            }
+            _ => panic!("expected suggestions"),
+        }
+
+        // Distance-1 matches found by the shortcut are ranked together:
+        // "helpo" -> "help" (deletion) and "hello" (substitution).
+        match corrector.suggest_single_word_corrections("helpo", 1) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "help"),
+            _ => panic!("expected suggestions"),
+        }
+        assert_eq!(
+            corrector.one_deletion_fast_path("helpo", 2).unwrap().len(),
+            2
+        );
+    }
+
+    #[test]
+    fn test_export_import_index_round_trip() {
+        let words = vec!["hello".to_string(), "help".to_string(), "world".to_string()];
+        let corrector = SpellCorrector::new(words.clone(), 2);
+        let path =
+            std::env::temp_dir().join(format!("spellcheck_index_{}.bin", std::process::id()));
+        let path = path.to_str().unwrap();
+        corrector.export_index(path).unwrap();
+
+        let mut imported = SpellCorrector::new(words.clone(), 2);
+        imported.dictionary.dictionary_del_mappings.clear();
+        imported.import_index(path).unwrap();
+        assert_eq!(
+            imported.dictionary.dictionary_del_mappings,
+            corrector.dictionary.dictionary_del_mappings
+        );
+        // "help" and "hello" tie at distance 1; "help" is closer in length
+        match imported.suggest_single_word_corrections("helo", 2) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "help"),
+            _ => panic!("expected suggestions"),
+        }
+
+        // an index only fits the dictionary and distance it was built for
+        let mut other_distance = SpellCorrector::new(words.clone(), 1);
+        assert!(other_distance.import_index(path).is_err());
+        let mut other_words = SpellCorrector::new(vec!["hello".to_string()], 2);
+        let before = other_words.dictionary.dictionary_del_mappings.clone();
+        assert!(other_words.import_index(path).is_err());
+        assert_eq!(other_words.dictionary.dictionary_del_mappings, before);
+
+        // corrupt counts are reported, not trusted with an allocation
+        let good = fs::read(path).unwrap();
+        let key_len = u32::from_le_bytes(good[28..32].try_into().unwrap()) as usize;
+        let n_postings_at = 32 + key_len;
+        for count_at in [24, n_postings_at] {
+            let mut corrupt = good.clone();
+            corrupt[count_at..count_at + 4].copy_from_slice(&u32::MAX.to_le_bytes());
+            fs::write(path, &corrupt).unwrap();
+            let mut reimported = SpellCorrector::new(words.clone(), 2);
+            assert!(reimported.import_index(path).is_err());
+        }
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_normalized_ranking() {
+        let words = vec![("at".to_string(), 100), ("attend".to_string(), 1)];
+        let corrector = SpellCorrector::new_with_frequencies(words, 2);
+        let ranked = |corrector: &SpellCorrector| match corrector
+            .suggest_single_word_corrections("atte", 2)
+        {
+            SuggestedCorrection::Suggestions(list) => {
+                assert!(list.iter().all(|s| s.distance == 2));
+                list.into_iter().map(|s| s.word).collect::<Vec<_>>()
+            }
+            _ => panic!("expected suggestions"),
+        };
+        // by raw distance the tie goes to the more frequent short word
+        assert_eq!(ranked(&corrector), vec!["at", "attend"]);
+
+        let corrector = corrector.with_normalized_ranking(true);
+        assert_eq!(ranked(&corrector), vec!["attend", "at"]);
+        let suggestion = Suggestion {
+            word: "attend".to_string(),
+            distance: 2,
+            frequency: 1,
+            metadata: None,
+        };
+        assert!((suggestion.normalized_distance() - 2.0 / 6.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_real_word_mode() {
+        let dict: Vec<String> = ["from", "form", "farm", "to"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1);
+        assert!(matches!(
+            corrector.suggest_single_word_corrections("form", 3),
+            SuggestedCorrection::NoSuggestions
+        ));
+
+        let corrector = corrector.with_real_word_mode(true);
+        match corrector.suggest_single_word_corrections("form", 3) {
+            SuggestedCorrection::Suggestions(list) => {
+                let words: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                assert_eq!(words, vec!["farm"]);
+            }
+            _ => panic!("expected neighbours of a valid word"),
+        }
+        match corrector.suggest_single_word_corrections("frm", 3) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list.len(), 3),
+            _ => panic!("expected suggestions"),
+        }
+        // dictionary words are left as they are, misspellings still fixed
+        assert_eq!(corrector.correct_text("form tto"), "form to");
+    }
+
+    #[test]
+    fn test_memory_budget_caps_distance() {
+        let dict: Vec<String> = ["spelling", "corrector", "dictionary", "memory"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let full = SpellCorrector::estimate_index_bytes(&dict, 2);
+        assert!(SpellCorrector::estimate_index_bytes(&dict, 1) < full);
+
+        // C(64, 31) * 33 overflows a u64 on the way to C(64, 32), but the
+        // total number of variants still fits
+        assert_eq!(
+            SpellCorrector::deletion_variant_count(64, 32),
+            10_139_684_107_326_071_075
+        );
+        assert_eq!(SpellCorrector::deletion_variant_count(64, 64), usize::MAX);
+        assert_eq!(SpellCorrector::deletion_variant_count(5, 2), 1 + 5 + 10);
+
+        let (corrector, stats) = SpellCorrector::new_with_memory_budget(dict.clone(), 2, full);
+        assert_eq!(stats.max_edit_distance, 2);
+        assert_eq!(corrector.max_edit_distance(), 2);
+
+        let (corrector, stats) = SpellCorrector::new_with_memory_budget(dict.clone(), 2, full - 1);
+        assert_eq!(stats.max_edit_distance, 1);
+        assert_eq!(corrector.max_edit_distance(), 1);
+        match corrector.suggest_single_word_corrections("speling", 1) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "spelling"),
+            _ => panic!("expected suggestions"),
+        }
+
+        let (_, stats) = SpellCorrector::new_with_memory_budget(dict, 2, 0);
+        assert_eq!(stats.max_edit_distance, 0);
+    }
+
+    #[test]
+    #[should_panic(expected = "Corrupt spell corrector file")]
+    fn test_load_rejects_out_of_range_index() {
+        let path =
+            std::env::temp_dir().join(format!("spellcheck_corrupt_{}.json", std::process::id()));
+        let path = path.to_str().unwrap().to_string();
+        let data = serde_json::json!({
+            "dictionary": vec!["cat".to_string()],
+            "dictionary_del_mappings": HashMap::from([("ca".to_string(), vec![0usize, 7])]),
+            "max_edit_distance": 1usize,
+        });
+        fs::write(&path, data.to_string()).unwrap();
+        let result = std::panic::catch_unwind(|| SpellCorrector::load_spell_corrector(&path));
+        fs::remove_file(&path).unwrap();
+        if let Err(panic) = result {
+            std::panic::resume_unwind(panic);
+        }
+    }
+
+    #[test]
+    fn test_num_candidates_within() {
+        let dict: Vec<String> = ["cat", "bat", "hat", "cart", "dog"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 2);
+        assert_eq!(corrector.num_candidates_within("cat", 0), 1);
+        assert_eq!(corrector.num_candidates_within("cat", 1), 4);
+        assert_eq!(corrector.num_candidates_within("xat", 1), 3);
+        // capped at the index depth
+        assert_eq!(corrector.num_candidates_within("cat", 9), 4);
+        assert_eq!(corrector.num_candidates_within("zzzzz", 2), 0);
+    }
+
+    #[test]
+    fn test_length_ratio_guard() {
+        let dict: Vec<String> = ["apple", "ax"].iter().map(|s| s.to_string()).collect();
+        let corrector = SpellCorrector::new(dict, 4);
+        assert_eq!(corrector.correct_text("appel"), "Apple");
+        assert_eq!(corrector.correct_text("aple"), "Apple");
+
+        let corrector = corrector.with_length_ratio_guard(0.5, 2.0);
+        assert_eq!(corrector.correct_text("aple"), "Apple");
+        match corrector.suggest_single_word_corrections("a", 3) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "ax"),
+            _ => panic!("expected suggestions"),
+        }
+        assert_eq!(corrector.best_correction("a").unwrap().word, "ax");
+        assert!(corrector.best_correction("pple").is_some());
+
+        let dict: Vec<String> = vec!["apple".to_string()];
+        let corrector = SpellCorrector::new(dict, 4).with_length_ratio_guard(0.5, 2.0);
+        // "a" is four insertions from "apple", but five times shorter
+        assert_eq!(corrector.correct_text("a"), "a");
+        assert!(corrector.best_correction("a").is_none());
+        let unguarded = SpellCorrector::new(vec!["apple".to_string()], 4);
+        assert_eq!(unguarded.correct_text("a"), "Apple");
+    }
+
+    #[test]
+    fn test_from_directory() {
+        let dir = std::env::temp_dir().join(format!("spellcheck_dir_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(dir.join("places.txt"), "London\nParis\n").unwrap();
+        fs::write(dir.join("names.txt"), "alice\nparis\n").unwrap();
+        fs::write(dir.join("notes.md"), "ignored\n").unwrap();
+
+        let (corrector, sources) =
+            SpellCorrector::from_directory_with_sources(dir.to_str().unwrap(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(corrector.dictionary.words, vec!["alice", "paris", "london"]);
+        assert!(!corrector.contains("ignored"));
+        assert_eq!(sources["paris"], vec!["names", "places"]);
+        assert_eq!(sources["london"], vec!["places"]);
+        match corrector.suggest_single_word_corrections("londn", 1) {
+            SuggestedCorrection::Suggestions(list) => assert_eq!(list[0].word, "london"),
+            _ => panic!("expected suggestions"),
+        }
+    }
+
+    #[cfg(feature = "smallvec")]
+    #[test]
+    fn test_small_suggestions_match_vec() {
+        let dict: Vec<String> = ["cat", "bat", "hat", "cart", "mat", "rat", "vat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1);
+        assert!(
+            corrector
+                .suggest_single_word_corrections_small("cat", 3)
+                .is_none()
+        );
+        for n in [3, 5, 10] {
+            // second round is served from the cache
+            for _ in 0..2 {
+                let small = corrector
+                    .suggest_single_word_corrections_small("xat", n)
+                    .unwrap();
+                let SuggestedCorrection::Suggestions(list) =
+                    corrector.suggest_single_word_corrections("xat", n)
+                else {
+                    panic!("expected suggestions");
+                };
+                let small: Vec<&str> = small.iter().map(|s| s.word.as_str()).collect();
+                let list: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                assert_eq!(small, list);
+                assert_eq!(small.len(), n.min(6));
+            }
+        }
+        let batch = corrector.suggest_word_corrections_small(&["xat".to_string()], 2);
+        assert_eq!(batch[0].as_ref().unwrap().len(), 2);
+    }
+
+    #[cfg(feature = "sampling")]
+    #[test]
+    fn test_suggest_sampled_is_reproducible() {
+        use rand::SeedableRng;
+        use rand::rngs::StdRng;
+
+        let words = vec![
+            ("cat".to_string(), 30),
+            ("bat".to_string(), 20),
+            ("hat".to_string(), 10),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 1);
+        let firsts = |seed: u64, n: usize| -> Vec<String> {
+            let mut rng = StdRng::seed_from_u64(seed);
+            (0..32)
+                .map(|_| match corrector.suggest_sampled("xat", n, &mut rng) {
+                    SuggestedCorrection::Suggestions(list) => {
+                        assert_eq!(list.len(), n);
+                        list[0].word.clone()
+                    }
+                    _ => panic!("expected suggestions"),
+                })
+                .collect()
+        };
+
+        assert_eq!(firsts(7, 2), firsts(7, 2));
+        let picks = firsts(7, 2);
+        assert!(picks.iter().all(|w| w == "cat" || w == "bat"));
+        assert!(picks.iter().any(|w| w == "bat"));
+        assert!(firsts(7, 1).iter().all(|w| w == "cat"));
+    }
+
+    #[test]
+    fn test_word_metadata_on_suggestions() {
+        let dict: Vec<String> = ["paris", "parish"].iter().map(|s| s.to_string()).collect();
+        let mut corrector = SpellCorrector::new(dict, 1);
+        corrector.set_word_metadata(
+            "paris",
+            HashMap::from([
+                ("pos".to_string(), "proper noun".to_string()),
+                ("domain".to_string(), "places".to_string()),
+            ]),
+        );
+        assert_eq!(
+            corrector.word_metadata("paris").unwrap()["pos"],
+            "proper noun"
+        );
+        assert!(corrector.word_metadata("parish").is_none());
+
+        match corrector.suggest_single_word_corrections("pariss", 2) {
+            SuggestedCorrection::Suggestions(list) => {
+                let paris = list.iter().find(|s| s.word == "paris").unwrap();
+                assert_eq!(paris.metadata.as_ref().unwrap()["domain"], "places");
+                let parish = list.iter().find(|s| s.word == "parish").unwrap();
+                assert!(parish.metadata.is_none());
+            }
+            _ => panic!("expected suggestions"),
+        }
+
+        let path =
+            std::env::temp_dir().join(format!("spellcheck_meta_{}.json", std::process::id()));
+        let path = path.to_str().unwrap();
+        corrector.save_spell_corrector(path).unwrap();
+        let loaded = SpellCorrector::load_spell_corrector(path);
+        fs::remove_file(path).unwrap();
+        assert_eq!(loaded.word_metadata("paris").unwrap()["domain"], "places");
+    }
+
+    #[test]
+    fn test_min_batch_len_keeps_results() {
+        let dict: Vec<String> = ["cat", "bat", "dog", "house", "mouse"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let words: Vec<String> = ["cst", "dog", "huose", "xxxxxxxx", "mousr"]
+            .iter()
+            .cycle()
+            .take(50)
+            .map(|s| s.to_string())
+            .collect();
+        let summary = |results: Vec<SuggestedCorrection>| -> Vec<Option<String>> {
+            results
+                .into_iter()
+                .map(|r| match r {
+                    SuggestedCorrection::NoSuggestions => None,
+                    SuggestedCorrection::Suggestions(list) => {
+                        Some(list.first().map(|s| s.word.clone()).unwrap_or_default())
+                    }
+                })
+                .collect()
+        };
+        let default = SpellCorrector::new(dict.clone(), 2);
+        let expected = summary(default.suggest_word_corrections(&words, 2));
+        for min_len in [0, 7, 1000] {
+            let corrector = SpellCorrector::new(dict.clone(), 2).with_min_batch_len(min_len);
+            assert_eq!(
+                summary(corrector.suggest_word_corrections(&words, 2)),
+                expected
+            );
+            assert_eq!(corrector.find_unknown_words(&words).len(), 40);
+        }
+    }
+
+    #[test]
+    fn test_closest_length_without_frequencies() {
+        let dict: Vec<String> = ["cat", "cast", "cart", "catalog"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict.clone(), 2);
+        let ranked = |corrector: &SpellCorrector, query: &str| match corrector
+            .suggest_single_word_corrections(query, 4)
+        {
+            SuggestedCorrection::Suggestions(list) => {
+                list.into_iter().map(|s| s.word).collect::<Vec<_>>()
+            }
+            _ => panic!("expected suggestions"),
+        };
+        // "cat" and "cast" are both one edit from "cst"; "cat" matches its length
+        assert_eq!(ranked(&corrector, "cst"), vec!["cat", "cast", "cart"]);
+        // all three are one edit from "caat", where the four-letter words fit better
+        assert_eq!(ranked(&corrector, "caat"), vec!["cart", "cast", "cat"]);
+        assert_eq!(ranked(&corrector, "catalo")[0], "catalog");
+
+        // frequencies, when present, still decide first
+        let words = dict.into_iter().map(|w| {
+            let count = if w == "cast" { 10 } else { 0 };
+            (w, count)
+        });
+        let corrector = SpellCorrector::new_with_frequencies(words.collect(), 2);
+        assert_eq!(ranked(&corrector, "cst"), vec!["cast", "cat", "cart"]);
+    }
+
+    #[test]
+    fn test_correct_stream_keeps_lines() {
+        let dict: Vec<String> = ["the", "cat", "sat", "on", "mat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 2);
+        let input = "teh cat sta\r\non the\n\nmat. teh cat\n";
+        let mut output = Vec::new();
+        corrector
+            .correct_stream(input.as_bytes(), &mut output)
+            .unwrap();
+        // the sentence carries on over the first line break
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "The cat sat\r\non the\n\nmat. The cat\n"
+        );
+    }
+
+    #[test]
+    fn test_correct_text_beam_beats_greedy() {
+        let words = vec![
+            ("now".to_string(), 100),
+            ("new".to_string(), 50),
+            ("york".to_string(), 10),
+        ];
+        let bigrams = HashMap::from([(("new".to_string(), "york".to_string()), 1000)]);
+        let corrector =
+            SpellCorrector::new_with_frequencies(words, 1).with_bigram_frequencies(bigrams);
+
+        // on its own "nw" is most likely "now"
+        assert_eq!(corrector.correct_text("nw yrk"), "Now york");
+        assert_eq!(corrector.correct_text_beam("nw yrk", 2), "New york");
+        // a beam of one commits to "now" before seeing "yrk"
+        assert_eq!(corrector.correct_text_beam("nw yrk", 1), "Now york");
+        assert_eq!(
+            corrector.correct_text_beam("I saw nw, yrk!", 3),
+            "I saw new, york!"
+        );
+    }
+
+    #[test]
+    fn test_suggested_correction_accessors() {
+        let corrector = SpellCorrector::new_with_frequencies(
+            vec![("hello".to_string(), 5), ("help".to_string(), 9)],
+            1,
+        );
+        let correct = corrector.suggest_single_word_corrections("hello", 3);
+        assert!(correct.is_correct());
+        assert!(correct.suggestions().is_empty());
+        assert!(correct.best().is_none());
+
+        let result = corrector.suggest_single_word_corrections("helo", 3);
+        assert!(!result.is_correct());
+        assert_eq!(result.best().unwrap().word, "help");
+        assert_eq!(result.suggestions().len(), 2);
+        let words: Vec<String> = result
+            .into_suggestions()
+            .into_iter()
+            .map(|s| s.word)
+            .collect();
+        assert_eq!(words, vec!["help", "hello"]);
+    }
+
+    #[test]
+    fn test_min_distance_candidate() {
+        let corrector = SpellCorrector::new(
+            vec!["hello".to_string(), "world".to_string(), "help".to_string()],
+            2,
+        );
+        assert_eq!(corrector.min_distance_candidate("hello"), Some(0));
+        assert_eq!(corrector.min_distance_candidate("helo"), Some(1));
+        assert_eq!(corrector.min_distance_candidate("wrd"), Some(2));
+        assert_eq!(corrector.min_distance_candidate("xyzzy"), None);
+
+        // a word's own limit applies, as in the suggestion lists
+        let corrector = SpellCorrector::new(vec!["cat".to_string()], 1)
+            .with_word_max_edit_distances(HashMap::from([("cat".to_string(), 0)]));
+        assert_eq!(corrector.min_distance_candidate("cot"), None);
+        assert!(
+            corrector
+                .suggest_single_word_corrections("cot", 1)
+                .into_suggestions()
+                .is_empty()
+        );
+    }
+
+    #[test]
+    fn test_lcs_tie_break() {
+        // both are two edits from "abxd"; "axbd" keeps three of its letters in
+        // order, "abxdef" all four, but is longer than the query
+        let words = vec!["axbd".to_string(), "abxdef".to_string()];
+        let corrector = SpellCorrector::new(words, 2);
+        let ranked = |corrector: &SpellCorrector| -> Vec<String> {
+            corrector
+                .suggest_single_word_corrections("abxd", 2)
+                .into_suggestions()
+                .into_iter()
+                .map(|s| s.word)
+                .collect()
+        };
+        assert_eq!(ranked(&corrector), vec!["axbd", "abxdef"]);
+        let corrector = corrector.with_lcs_tie_break(true);
+        assert_eq!(ranked(&corrector), vec!["abxdef", "axbd"]);
+    }
+
+    #[test]
+    fn test_correct_text_cow_borrows_clean_text() {
+        let words = vec!["the".to_string(), "cat".to_string(), "sat".to_string()];
+        let corrector = SpellCorrector::new(words, 1);
+        let clean = "The cat sat.";
+        assert!(matches!(corrector.correct_text_cow(clean), Cow::Borrowed(c) if c == clean));
+        let corrected = corrector.correct_text_cow("The cat sst.");
+        assert!(matches!(&corrected, Cow::Owned(c) if c == "The cat sat."));
+        // collapsing whitespace is a change too
+        assert!(matches!(
+            corrector.correct_text_cow("The  cat"),
+            Cow::Owned(_)
+        ));
+    }
+
+    #[test]
+    fn test_per_thread_cache() {
+        let words = vec!["hello".to_string(), "help".to_string(), "world".to_string()];
+        let shared = SpellCorrector::new(words, 1);
+        let per_thread = shared.clone().with_per_thread_cache(true);
+        let queries: Vec<String> = ["helo", "wrld", "helo", "hel", "wrld"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        for _ in 0..2 {
+            let a = shared.suggest_word_corrections(&queries, 2);
+            let b = per_thread.suggest_word_corrections(&queries, 2);
+            let words = |results: Vec<SuggestedCorrection>| -> Vec<Vec<String>> {
+                results
+                    .into_iter()
+                    .map(|r| r.into_suggestions().into_iter().map(|s| s.word).collect())
+                    .collect()
+            };
+            assert_eq!(words(a), words(b));
+        }
+        // the per-thread caches are cleared along with the shared one
+        let mut per_thread = per_thread;
+        assert_eq!(
+            per_thread
+                .suggest_single_word_corrections("helo", 2)
+                .suggestions()
+                .len(),
+            2
+        );
+        per_thread.set_word_max_edit_distance("help", 0);
+        assert_eq!(
+            per_thread
+                .suggest_single_word_corrections("helo", 2)
+                .suggestions()
+                .len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_wildcard_queries() {
+        let words = vec![
+            ("spelling".to_string(), 10),
+            ("spilling".to_string(), 5),
+            ("selling".to_string(), 20),
+            ("spell".to_string(), 3),
+            ("spells".to_string(), 2),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 1).with_wildcards(true);
+        let ranked = |corrector: &SpellCorrector, query: &str| -> Vec<(String, usize)> {
+            corrector
+                .suggest_single_word_corrections(query, 5)
+                .into_suggestions()
+                .into_iter()
+                .map(|s| (s.word, s.distance))
+                .collect()
+        };
+        let expected = vec![
+            ("spelling".to_string(), 0),
+            ("spilling".to_string(), 0),
+            ("selling".to_string(), 1),
+        ];
+        assert_eq!(ranked(&corrector, "sp*lling"), expected);
+        assert_eq!(ranked(&corrector, "sp*ll*ng"), expected);
+        assert_eq!(
+            ranked(&corrector, "spe*l?"),
+            vec![("spell".to_string(), 0), ("spells".to_string(), 0)]
+        );
+
+        // without the flag `*` is just another character
+        let plain = corrector.clone().with_wildcards(false);
+        assert!(ranked(&plain, "sp*lling").iter().all(|(_, d)| *d == 1));
+    }
+
+    #[test]
+    fn test_negative_cache_eviction() {
+        let dict = vec!["spelling".to_string()];
+        let corrector = SpellCorrector::new(dict, 1).with_negative_cache_capacity(3);
+        // a recurring unfixable token builds up hits...
+        for _ in 0..3 {
+            let _ = corrector.suggest_single_word_corrections("qqqq", 1);
+        }
+        // ...and survives a flood of one-off ones
+        let garbage: Vec<String> = (0..20).map(|i| format!("zz{}zz", i)).collect();
+        for word in &garbage {
+            let _ = corrector.suggest_single_word_corrections(word, 1);
+        }
+        let cache = corrector.negative_cache.as_ref().unwrap();
+        assert!(cache.get(&"qqqq".to_string()).is_some());
+        let cached = garbage.iter().filter(|w| cache.get(w).is_some()).count();
+        assert!(cached <= 2, "{} one-off words still cached", cached);
+        assert!(cached >= 1);
+    }
+
+    #[test]
+    fn test_contractions_and_possessives() {
+        let words: Vec<String> = ["this", "the", "dogs", "bone", "was", "them"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(words, 1);
+        assert_eq!(
+            corrector.correct_text("'Tis the dogs' bone, get 'em."),
+            "'Tis the dogs' bone, get 'em."
+        );
+        assert_eq!(corrector.correct_text("the dogs' bon"), "the dogs' bone");
+        assert_eq!(
+            corrector.correct_text_beam("'Tis the dogs' bon", 2),
+            "'Tis the dogs' bone"
+        );
+
+        let corrector = corrector.with_contractions(["'Twas"]);
+        assert_eq!(
+            corrector.correct_text("\u{2019}twas 'tis"),
+            "\u{2019}twas 'this"
+        );
+    }
+
+    #[test]
+    fn test_split_runon() {
+        let words = vec![
+            ("a".to_string(), 100),
+            ("lot".to_string(), 20),
+            ("al".to_string(), 1),
+            ("in".to_string(), 50),
+            ("fact".to_string(), 10),
+            ("inf".to_string(), 1),
+            ("act".to_string(), 5),
+            ("spelling".to_string(), 5),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 1);
+        let split = |word: &str| -> Option<(String, String)> { corrector.split_runon(word) };
+        let pair = |l: &str, r: &str| Some((l.to_string(), r.to_string()));
+        assert_eq!(split("alot"), pair("a", "lot"));
+        // "in fact" beats "inf act" on frequency
+        assert_eq!(split("infact"), pair("in", "fact"));
+        // one half may be misspelled
+        assert_eq!(split("infakt"), pair("in", "fact"));
+        assert_eq!(split("spelling"), None);
+        assert_eq!(split("xyzzyq"), None);
+    }
+
+    #[test]
+    fn test_suggest_with_match_kind() {
+        let words = vec!["hello".to_string(), "world".to_string()];
+        let corrector = SpellCorrector::new(words, 1).with_case_insensitive(true);
+
+        let (kind, result) = corrector.suggest_with_match_kind("hello", 3);
+        assert_eq!(kind, MatchKind::Exact);
+        assert!(result.is_correct());
+
+        let (kind, result) = corrector.suggest_with_match_kind("Hello", 3);
+        assert_eq!(kind, MatchKind::Normalized);
+        assert!(result.is_correct());
+
+        let (kind, result) = corrector.suggest_with_match_kind("Helo", 3);
+        assert_eq!(kind, MatchKind::Corrected);
+        assert_eq!(result.best().unwrap().word, "hello");
+
+        let (kind, result) = corrector.suggest_with_match_kind("qqqqq", 3);
+        assert_eq!(kind, MatchKind::Corrected);
+        assert!(result.suggestions().is_empty());
+
+        // without normalization "Hello" is just a misspelling
+        let corrector = corrector.with_case_insensitive(false);
+        assert_eq!(
+            corrector.suggest_with_match_kind("Hello", 3).0,
+            MatchKind::Corrected
+        );
+    }
+
+    #[test]
+    fn test_small_batches_run_serially() {
+        use std::sync::Mutex;
+        use std::thread::ThreadId;
+
+        let threads: Arc<Mutex<HashSet<ThreadId>>> = Arc::default();
+        let recorded = threads.clone();
+        let words = vec!["hello".to_string(), "help".to_string()];
+        let corrector = SpellCorrector::new(words, 1)
+            .with_distance_fn(move |a, b, max| {
+                recorded.lock().unwrap().insert(std::thread::current().id());
+                bounded_levenshtein(a, b, max)
+            })
+            .with_parallel_threshold(8);
+
+        let batch: Vec<String> = (0..7).map(|i| format!("helo{}", i)).collect();
+        let results = corrector.suggest_word_corrections(&batch, 1);
+        assert_eq!(results.len(), 7);
+        let threads = threads.lock().unwrap();
+        assert_eq!(*threads, HashSet::from([std::thread::current().id()]));
+    }
+
+    #[test]
+    fn test_best_correction_diff() {
+        let corrector = SpellCorrector::new(vec!["spelling".to_string()], 2);
+        let (best, diff) = corrector.best_correction_diff("speling").unwrap();
+        assert_eq!(best.word, "spelling");
+        assert_eq!(
+            diff,
+            vec![
+                DiffSpan::Unchanged("spe".to_string()),
+                DiffSpan::Inserted("l".to_string()),
+                DiffSpan::Unchanged("ling".to_string()),
+            ]
+        );
+        assert!(corrector.best_correction_diff("spelling").is_none());
+    }
+
+    #[test]
+    fn test_numeric_tokens() {
+        let words: Vec<String> = ["ran", "at", "up", "pi", "is", "km", "a"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(words, 2);
+        let text = "Ran 50km at 20%, pi is 3.14.";
+        assert_ne!(corrector.correct_text(text), text);
+
+        let corrector = corrector.with_numeric_tokens(true);
+        assert_eq!(corrector.correct_text(text), text);
+        assert_eq!(corrector.correct_text_beam(text, 2), text);
+        assert_eq!(corrector.correct_text("ran 50km upp"), "ran 50km up");
+
+        let corrector = corrector.with_numeric_units(["mi"]);
+        assert_eq!(corrector.correct_text("ran 5mi at 20%"), "ran 5mi at 20%");
+        assert_ne!(corrector.correct_text("ran 50km"), "ran 50km");
+    }
+
+    #[test]
+    fn test_most_ambiguous_words() {
+        let words: Vec<String> = ["bat", "cat", "hat", "mat", "cart", "dog", "elephant"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(words, 2);
+        let pair = |w: &str, n| (w.to_string(), n);
+        assert_eq!(
+            corrector.most_ambiguous_words(1, 2),
+            vec![pair("cat", 4), pair("bat", 3)]
+        );
+        let all = corrector.most_ambiguous_words(1, 10);
+        assert_eq!(all.len(), 5);
+        assert!(all.iter().all(|(w, _)| w != "dog" && w != "elephant"));
+    }
+
+    #[test]
+    fn test_correction_override() {
+        let words = vec![
+            ("the".to_string(), 1),
+            ("ten".to_string(), 500),
+            ("tech".to_string(), 100),
+        ];
+        let mut corrector = SpellCorrector::new_with_frequencies(words, 1);
+        assert_eq!(corrector.best_correction("teh").unwrap().word, "ten");
+
+        corrector.add_correction_override("teh", "the");
+        let result = corrector.suggest_single_word_corrections("teh", 3);
+        assert_eq!(result.suggestions().len(), 1);
+        let best = result.best().unwrap();
+        assert_eq!(
+            (best.word.as_str(), best.distance, best.frequency),
+            ("the", 2, 1)
+        );
+        assert_eq!(corrector.correct_text("Teh cat"), "The cat");
+    }
+
+    // every deletion of every variant, level by level, with no shortcuts
+    fn naive_deletion_variants(word: &str, max_del: usize, keep_original: bool) -> HashSet<String> {
+        let mut all: HashSet<String> = HashSet::new();
+        let mut level: HashSet<String> = [word.to_string()].into();
+        for _ in 0..max_del {
+            level = level
+                .iter()
+                .flat_map(|v| {
+                    let chars: Vec<char> = v.chars().collect();
+                    (0..chars.len())
+                        .map(|i| chars[..i].iter().chain(&chars[i + 1..]).collect::<String>())
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+            all.extend(level.iter().cloned());
+        }
+        if keep_original {
+            all.insert(word.to_string());
+        }
+        all
+    }
+
+    proptest! {
+        // small alphabet so words have plenty of repeated letters
+        #[test]
+        fn prop_deletion_variants_match_naive_expansion(
+            word in "[abcé]{0,7}",
+            max_del in 0usize..4,
+            keep_original in any::<bool>(),
+        ) {
+            prop_assert_eq!(
+                deletion_variants(&word, max_del, keep_original),
+                naive_deletion_variants(&word, max_del, keep_original)
+            );
+        }
+    }
+
+    #[test]
+    fn test_complete_or_correct() {
+        let words = vec![
+            ("spell".to_string(), 30),
+            ("spelling".to_string(), 50),
+            ("spelled".to_string(), 10),
+            ("spiel".to_string(), 40),
+            ("sell".to_string(), 5),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 1);
+        let words_of = |list: Vec<(MatchKind, Suggestion)>| -> Vec<String> {
+            list.into_iter().map(|(_, s)| s.word).collect()
+        };
+
+        let list = corrector.complete_or_correct("spel", 3);
+        assert_eq!(list[0].0, MatchKind::Completion);
+        assert_eq!(list[0].1.distance, 4);
+        assert_eq!(words_of(list), vec!["spelling", "spell", "spelled"]);
+        // completions first, then corrections of the prefix itself, each
+        // marked so chars left are not read as edits
+        let list = corrector.complete_or_correct("spel", 5);
+        let kinds: Vec<MatchKind> = list.iter().map(|(kind, _)| *kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                MatchKind::Completion,
+                MatchKind::Completion,
+                MatchKind::Completion,
+                MatchKind::Corrected
+            ]
+        );
+        assert_eq!(
+            words_of(list),
+            vec!["spelling", "spell", "spelled", "spiel"]
+        );
+        // a misspelled prefix has no completions, only corrections
+        assert_eq!(
+            words_of(corrector.complete_or_correct("spekl", 3)),
+            vec!["spell"]
+        );
+
+        // completions are held to the length cap like corrections
+        let capped = corrector.with_max_suggestion_len(5);
+        assert_eq!(
+            words_of(capped.complete_or_correct("spel", 5)),
+            vec!["spell", "spiel"]
+        );
+    }
+
+    #[cfg(feature = "logging")]
+    #[test]
+    fn test_slow_query_logging() {
+        use std::sync::Mutex;
+
+        struct CaptureLogger(Mutex<Vec<(log::Level, String)>>);
+
+        impl log::Log for CaptureLogger {
+            fn enabled(&self, _metadata: &log::Metadata) -> bool {
+                true
+            }
+            fn log(&self, record: &log::Record) {
+                let message = record.args().to_string();
+                self.0.lock().unwrap().push((record.level(), message));
+            }
+            fn flush(&self) {}
+        }
+
+        static LOGGER: CaptureLogger = CaptureLogger(Mutex::new(Vec::new()));
+        let _ = log::set_logger(&LOGGER);
+        log::set_max_level(log::LevelFilter::Trace);
+
+        let dict = ["slowa", "slowb", "slowc", "slowd", "quick"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1).with_slow_query_threshold(2);
+        let _ = corrector.suggest_single_word_corrections("slowz", 5);
+        let _ = corrector.suggest_single_word_corrections("slowz", 5);
+        let _ = corrector.suggest_single_word_corrections("quack", 5);
+
+        // other tests may log concurrently, so only look at our queries
+        let records = LOGGER.0.lock().unwrap();
+        let ours = |w: &str| -> Vec<(log::Level, String)> {
+            records
+                .iter()
+                .filter(|(_, m)| m.contains(w))
+                .cloned()
+                .collect()
+        };
+        let slow = ours("slowz");
+        assert!(slow.iter().any(|(level, m)| *level == log::Level::Warn
+            && m.contains("slow query")
+            && m.contains("4 candidates")));
+        assert!(
+            slow.iter()
+                .any(|(level, m)| *level == log::Level::Trace && m.contains("cache miss"))
+        );
+        assert!(slow.iter().any(|(_, m)| m.contains("cache hit")));
+        assert!(
+            !ours("quack")
+                .iter()
+                .any(|(level, _)| *level == log::Level::Warn)
+        );
+    }
+
+    #[test]
+    fn test_add_existing_word_merges_frequency() {
+        let mut corrector = SpellCorrector::new_with_frequencies(
+            vec![("cat".to_string(), 3), ("cot".to_string(), 5)],
+            1,
+        );
+        corrector.add_word_to_dictionary_with_frequency("cat", 4);
+        corrector.add_word_to_dictionary("cat");
+        assert_eq!(corrector.dictionary.words, vec!["cat", "cot"]);
+        assert_eq!(corrector.dictionary.dictionary_del_mappings["at"], vec![0]);
+        match corrector.suggest_single_word_corrections("cst", 2) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list[0].word, "cat");
+                assert_eq!(list[0].frequency, 7);
+            }
+            _ => panic!("expected suggestions"),
+        }
+    }
+
+    #[test]
+    fn test_diacritic_insensitive_matching() {
+        let dict = ["naïve", "résumé", "resume", "café", "cafe", "cave"]
+            .iter()
+            .map(|w| w.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1).with_diacritic_insensitive(true);
+        let words = |query: &str| match corrector.suggest_single_word_corrections(query, 5) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert!(list.iter().all(|s| s.distance == 0));
+                list.into_iter().map(|s| s.word).collect::<Vec<_>>()
+            }
+            SuggestedCorrection::NoSuggestions => Vec::new(),
+        };
+        assert_eq!(words("naive"), vec!["naïve"]);
+        let mut both = words("resumé");
+        both.sort();
+        assert_eq!(both, vec!["resume", "résumé"]);
+        // exact entries are still correct as typed
+        assert!(words("resume").is_empty());
+        assert!(words("café").is_empty());
+
+        let mut corrector =
+            SpellCorrector::new(vec!["naive".to_string()], 1).with_diacritic_insensitive(true);
+        assert_eq!(corrector.best_correction("naïve").unwrap().word, "naive");
+        corrector.add_word_to_dictionary("résumé");
+        assert_eq!(corrector.best_correction("resume").unwrap().word, "résumé");
+
+        let plain = SpellCorrector::new(vec!["résumé".to_string()], 1);
+        assert!(plain.best_correction("resume").is_none());
+    }
+
+    #[test]
+    fn test_streaming_builder() {
+        let mut builder = SpellCorrectorBuilder::new(2);
+        assert!(builder.is_empty());
+        let stream = ["spelling", "spilling", "selling", "spelling"];
+        for (i, word) in stream.iter().enumerate() {
+            builder.push_with_frequency(*word, i as u64 + 1);
+        }
+        builder.push(String::from("telling"));
+        assert_eq!(builder.len(), 4);
+
+        let corrector = builder.finish();
+        let batch = SpellCorrector::new_with_frequencies(
+            vec![
+                ("spelling".to_string(), 5),
+                ("spilling".to_string(), 2),
+                ("selling".to_string(), 3),
+                ("telling".to_string(), 0),
+            ],
+            2,
+        );
+        assert_eq!(corrector.dictionary.words, batch.dictionary.words);
+        assert_eq!(
+            corrector.dictionary.frequencies,
+            batch.dictionary.frequencies
+        );
+        assert_eq!(
+            corrector.dictionary.dictionary_del_mappings,
+            batch.dictionary.dictionary_del_mappings
+        );
+        assert_eq!(
+            corrector.best_correction("speling").unwrap().word,
+            "spelling"
+        );
+    }
+
+    #[test]
+    fn test_exclude_input_from_neighbours() {
+        let dict: Vec<String> = ["cat", "cot", "cut", "dog"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1).with_real_word_mode(true);
+        let neighbours = |corrector: &SpellCorrector| -> Vec<(String, usize)> {
+            match corrector.suggest_single_word_corrections("cat", 5) {
+                SuggestedCorrection::Suggestions(list) => {
+                    list.into_iter().map(|s| (s.word, s.distance)).collect()
+                }
+                _ => panic!("expected neighbours"),
+            }
+        };
+        let excluded = neighbours(&corrector);
+        assert!(excluded.iter().all(|(word, _)| word != "cat"));
+        assert_eq!(excluded.len(), 2);
+
+        let corrector = corrector.with_exclude_input(false);
+        let included = neighbours(&corrector);
+        assert_eq!(included[0], ("cat".to_string(), 0));
+        assert_eq!(&included[1..], &excluded[..]);
+    }
+
+    #[test]
+    fn test_exclude_input_with_one_deletion_neighbour() {
+        // "for" is "form" minus one char, which would take the depth-1 shortcut
+        let dict: Vec<String> = ["form", "for", "from", "fork", "foam"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1)
+            .with_real_word_mode(true)
+            .with_exclude_input(false);
+        match corrector.suggest_single_word_corrections("form", 2) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list.len(), 2);
+                assert_eq!((list[0].word.as_str(), list[0].distance), ("form", 0));
+            }
+            _ => panic!("expected the query back"),
+        }
+    }
+
+    #[test]
+    fn test_phonetic_tie_break() {
+        let dict: Vec<String> = ["shear", "their"].iter().map(|s| s.to_string()).collect();
+        let corrector = SpellCorrector::new(dict, 1);
+        let order = |corrector: &SpellCorrector| -> Vec<String> {
+            match corrector.suggest_single_word_corrections("thear", 2) {
+                SuggestedCorrection::Suggestions(list) => {
+                    assert!(list.iter().all(|s| s.distance == 1));
+                    list.into_iter().map(|s| s.word).collect()
+                }
+                _ => panic!("expected suggestions"),
+            }
+        };
+        // alphabetical by default
+        assert_eq!(order(&corrector), vec!["shear", "their"]);
+        let corrector = corrector.with_phonetic_tie_break(true);
+        assert_eq!(order(&corrector), vec!["their", "shear"]);
+    }
+
+    #[test]
+    fn test_zero_distance_is_exact_match_only() {
+        let dict: Vec<String> = ["cat", "dog"].iter().map(|s| s.to_string()).collect();
+        let mut corrector = SpellCorrector::new(dict, 0);
+        assert!(corrector.is_exact_match_only());
+        assert!(!SpellCorrector::new(vec!["cat".to_string()], 1).is_exact_match_only());
+        assert_eq!(corrector.dictionary.dictionary_del_mappings.len(), 2);
+
+        assert!(corrector.contains("cat"));
+        assert!(matches!(
+            corrector.suggest_single_word_corrections("cat", 3),
+            SuggestedCorrection::NoSuggestions
+        ));
+        match corrector.suggest_single_word_corrections("cot", 3) {
+            SuggestedCorrection::Suggestions(list) => assert!(list.is_empty()),
+            _ => panic!("a misspelling should get an empty list"),
+        }
+        assert_eq!(corrector.correct_text("cot dog"), "cot dog");
+
+        corrector.add_correction_override("cot", "cat");
+        assert_eq!(corrector.correct_text("cot dog"), "Cat dog");
+    }
+
+    #[test]
+    fn test_suggest_merged_across_correctors() {
+        let base = SpellCorrector::new_with_frequencies(
+            vec![("plain".to_string(), 40), ("slain".to_string(), 60)],
+            1,
+        );
+        let domain = SpellCorrector::new_with_frequencies(
+            vec![("blain".to_string(), 40), ("plain".to_string(), 40)],
+            1,
+        );
+        match SpellCorrector::suggest_merged("xlain", 5, &[&base, &domain]) {
+            SuggestedCorrection::Suggestions(list) => {
+                let words: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                // "plain" is listed once, and beats "blain" on priority
+                assert_eq!(words, vec!["slain", "plain", "blain"]);
+            }
+            _ => panic!("expected suggestions"),
+        }
+        match SpellCorrector::suggest_merged("xlain", 2, &[&domain, &base]) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list.len(), 2);
+                assert_eq!(list[1].word, "blain");
+            }
+            _ => panic!("expected suggestions"),
+        }
+        // a word from either dictionary is correct
+        assert!(matches!(
+            SpellCorrector::suggest_merged("blain", 3, &[&base, &domain]),
+            SuggestedCorrection::NoSuggestions
+        ));
+    }
+
+    #[test]
+    fn test_reset_keeps_configuration() {
+        let dict: Vec<String> = ["hello", "help"].iter().map(|s| s.to_string()).collect();
+        let mut corrector = SpellCorrector::new(dict, 2).with_case_insensitive(true);
+        assert_eq!(corrector.best_correction("helloo").unwrap().word, "hello");
+
+        corrector.reset();
+        assert!(corrector.dictionary.words.is_empty());
+        assert!(corrector.dictionary.dictionary_del_mappings.is_empty());
+        assert!(!corrector.contains("hello"));
+        assert!(corrector.best_correction("helo").is_none());
+
+        corrector.add_word_to_dictionary("Halo");
+        assert_eq!(corrector.dictionary.words, vec!["halo"]);
+        assert_eq!(corrector.max_edit_distance(), 2);
+        // still case-insensitive, and the index is fresh
+        assert!(corrector.contains("HALO"));
+        assert_eq!(corrector.best_correction("helo").unwrap().word, "halo");
+    }
+
+    #[test]
+    fn test_overlong_candidates_are_not_verified() {
+        // hands back every word for every key, like a sloppy custom store
+        struct EveryWord(Vec<&'static str>);
+        impl Dictionary for EveryWord {
+            fn len(&self) -> usize {
+                self.0.len()
+            }
+            fn contains(&self, word: &str) -> bool {
+                self.0.contains(&word)
+            }
+            fn get(&self, index: usize) -> Option<Cow<'_, str>> {
+                self.0.get(index).map(|w| Cow::Borrowed(*w))
+            }
+            fn deletion_candidates(&self, _del_key: &str) -> Cow<'_, [usize]> {
+                Cow::Owned((0..self.0.len()).collect())
+            }
+        }
+
+        let verified = Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = Arc::clone(&verified);
+        let corrector = SpellCorrector::from_dictionary(EveryWord(vec!["cats", "catalogue"]), 1)
+            .with_distance_fn(move |_, candidate, _| {
+                log.lock().unwrap().push(candidate.to_string());
+                0
+            });
+        match corrector.suggest_single_word_corrections("cat", 5) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list.len(), 1);
+                assert_eq!(list[0].word, "cats");
+            }
+            _ => panic!("expected suggestions"),
+        }
+        assert_eq!(*verified.lock().unwrap(), vec!["cats"]);
+
+        let dict: Vec<String> = ["spell", "spelling", "spelled"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 3).with_max_suggestion_len(6);
+        match corrector.suggest_single_word_corrections("spellin", 5) {
+            SuggestedCorrection::Suggestions(list) => {
+                let words: Vec<&str> = list.iter().map(|s| s.word.as_str()).collect();
+                assert_eq!(words, vec!["spell"]);
+            }
+            _ => panic!("expected suggestions"),
+        }
+    }
+
+    #[test]
+    fn test_record_acceptance_updates_ranking() {
+        let dict: Vec<String> = ["the", "ten", "tech"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut corrector = SpellCorrector::new(dict, 2);
+        let ranked = |corrector: &SpellCorrector, word: &str| -> Vec<String> {
+            match corrector.suggest_single_word_corrections(word, 3) {
+                SuggestedCorrection::Suggestions(list) => {
+                    list.into_iter().map(|s| s.word).collect()
+                }
+                _ => panic!("expected suggestions"),
+            }
+        };
+        assert_eq!(ranked(&corrector, "teh"), vec!["ten", "tech", "the"]);
+
+        // "the" is two edits away but was picked, so it comes first
+        corrector.record_acceptance("teh", "the");
+        assert_eq!(ranked(&corrector, "teh"), vec!["the", "ten", "tech"]);
+
+        corrector.record_acceptance("teh", "ten");
+        corrector.record_acceptance("teh", "ten");
+        assert_eq!(ranked(&corrector, "teh"), vec!["ten", "the", "tech"]);
+        // other queries are unaffected
+        assert_eq!(ranked(&corrector, "tem")[0], "ten");
+    }
+
+    #[test]
+    fn test_acronyms_kept_above_threshold() {
+        let dict: Vec<String> = ["the", "nasa", "nazi", "launch", "has"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 2);
+        // off by default: "NASB" is lowercased and corrected like any word
+        assert_eq!(corrector.correct_text("so NASB has"), "so nasa has");
+
+        let corrector = corrector.with_acronym_min_len(4);
+        assert_eq!(
+            corrector.correct_text("so NASB TEH launch."),
+            "so NASB the launch."
         );
+        assert_eq!(corrector.correct_text("NASA has"), "NASA has");
+        // a mixed-case word is not an acronym
+        assert_eq!(corrector.correct_text("so Nasb has"), "so nasa has");
+        assert_eq!(corrector.correct_text_beam("so NASB TEH", 2), "so NASB the");
+    }
 
-        match corrector.suggest_single_word_corrections("speling", 2) {
-            SuggestedCorrection::Suggestions(list) => {
-                assert_eq!(list[0].word, "spelling"); // distance 1
-                assert_eq!(list[1].word, "spilling"); // distance 2
-                assert_eq!(list.len(), 2);
+    #[test]
+    fn test_verify_roundtrip() {
+        let words = vec![
+            ("spelling".to_string(), 10),
+            ("spilling".to_string(), 20),
+            ("selling".to_string(), 5),
+            ("café".to_string(), 1),
+        ];
+        let mut corrector =
+            SpellCorrector::new_with_frequencies(words, 2).with_normalized_ranking(true);
+        corrector.set_word_max_edit_distance("selling", 1);
+        let metadata = HashMap::from([("pos".to_string(), "noun".to_string())]);
+        corrector.set_word_metadata("spelling", metadata);
+        assert!(corrector.verify_roundtrip(10).is_ok());
+        assert!(corrector.verify_roundtrip(0).is_ok());
+    }
+
+    #[test]
+    fn test_position_weighted_ranking() {
+        let dict: Vec<(String, u64)> = vec![
+            ("bat".to_string(), 10),
+            ("map".to_string(), 5),
+            ("mart".to_string(), 0),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(dict, 1);
+        let order = |corrector: &SpellCorrector| -> Vec<String> {
+            match corrector.suggest_single_word_corrections("mat", 3) {
+                SuggestedCorrection::Suggestions(list) => {
+                    list.into_iter().map(|s| s.word).collect()
+                }
+                _ => panic!("expected suggestions"),
+            }
+        };
+        assert_eq!(order(&corrector), vec!["bat", "map", "mart"]);
+        // "bat" changes the first letter, "mart" inserts before the last
+        let corrector = corrector.with_position_weighted_ranking(true);
+        assert_eq!(order(&corrector), vec!["map", "mart", "bat"]);
+    }
+
+    #[test]
+    fn test_index_report_lists_hot_keys() {
+        let dict: Vec<String> = ["cat", "bat", "hat", "rat", "mat", "vat", "dog"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1);
+        let report = corrector.index_report(2);
+        let lines: Vec<&str> = report.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("7 words, "));
+        assert_eq!(
+            lines[1],
+            "       6  \"at\"  bat, cat, hat, mat, rat (+1 more)"
+        );
+        assert!(lines[2].trim_start().starts_with('1'));
+        assert_eq!(corrector.index_report(0).lines().count(), 1);
+    }
+
+    #[test]
+    fn test_truncated_ties_are_reproducible() {
+        // candidates come out sorted by index on every build, so whichever of
+        // them tie through every comparator are verified and kept in a fixed
+        // order
+        let dict: Vec<String> = ["bat", "cab", "cad", "can", "cap", "car", "cut", "hat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let expected = SpellCorrector::new(dict.clone(), 1).candidate_indices("cat", 1);
+        assert!(expected.windows(2).all(|w| w[0] < w[1]));
+        for _ in 0..20 {
+            let corrector = SpellCorrector::new(dict.clone(), 1);
+            assert_eq!(corrector.candidate_indices("cat", 1), expected);
+        }
+    }
+
+    #[test]
+    fn test_correct_identifiers() {
+        let dict: Vec<String> = ["get", "user", "name", "count", "max", "retry", "the"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1);
+        assert_eq!(corrector.correct_identifier("getUsrName"), "getUserName");
+        assert_eq!(corrector.correct_identifier("GetUserNme"), "GetUserName");
+        assert_eq!(corrector.correct_identifier("usr_count"), "user_count");
+        assert_eq!(corrector.correct_identifier("MAX_RETRYS"), "MAX_RETRY");
+        assert_eq!(corrector.correct_identifier("user_count2"), "user_count2");
+
+        let corrector = corrector.with_identifier_splitting(true);
+        assert_eq!(
+            corrector.correct_text("call getUsrName(usr_count) thw"),
+            "call getUserName(user_count) the"
+        );
+    }
+
+    #[test]
+    fn test_suggest_progressive_tiers() {
+        let dict: Vec<String> = ["spell", "spelt", "shell", "smell", "spill", "spells"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 2);
+        let mut tiers: Vec<(usize, Vec<String>)> = Vec::new();
+        let result = corrector.suggest_progressive("spel", 10, |distance, tier| {
+            tiers.push((distance, tier.iter().map(|s| s.word.clone()).collect()));
+        });
+        assert_eq!(
+            tiers,
+            vec![
+                (1, vec!["spell".to_string(), "spelt".to_string()]),
+                (
+                    2,
+                    vec![
+                        "shell".to_string(),
+                        "smell".to_string(),
+                        "spill".to_string(),
+                        "spells".to_string()
+                    ]
+                ),
+            ]
+        );
+        match (
+            result,
+            corrector.suggest_single_word_corrections("spel", 10),
+        ) {
+            (SuggestedCorrection::Suggestions(a), SuggestedCorrection::Suggestions(b)) => {
+                let a: Vec<_> = a.into_iter().map(|s| s.word).collect();
+                let b: Vec<_> = b.into_iter().map(|s| s.word).collect();
+                assert_eq!(a, b);
             }
             _ => panic!("expected suggestions"),
         }
+
+        // enough close matches: the deeper tier is never searched
+        let mut calls = Vec::new();
+        let _ = corrector.suggest_progressive("spel", 2, |distance, _| calls.push(distance));
+        assert_eq!(calls, vec![1]);
+        let mut called = false;
+        let result = corrector.suggest_progressive("spell", 2, |_, _| called = true);
+        assert!(matches!(result, SuggestedCorrection::NoSuggestions) && !called);
     }
 
     #[test]
-    fn test_add_word_updates_dictionary() {
-        let dict: Vec<String> = ["cat"].iter().map(|s| s.to_string()).collect();
+    fn test_hyphen_insensitive_matching() {
+        let best = |corrector: &SpellCorrector, word: &str| -> Vec<(String, usize)> {
+            match corrector.suggest_single_word_corrections(word, 3) {
+                SuggestedCorrection::Suggestions(list) => {
+                    list.into_iter().map(|s| (s.word, s.distance)).collect()
+                }
+                SuggestedCorrection::NoSuggestions => Vec::new(),
+            }
+        };
+        let corrector = SpellCorrector::new(vec!["email".to_string(), "re-enter".to_string()], 1)
+            .with_hyphen_insensitive(true);
+        assert_eq!(best(&corrector, "e-mail"), vec![("email".to_string(), 0)]);
+        assert_eq!(
+            best(&corrector, "reenter"),
+            vec![("re-enter".to_string(), 0)]
+        );
+        assert_eq!(
+            best(&corrector, "ree-nter"),
+            vec![("re-enter".to_string(), 0)]
+        );
+        assert!(best(&corrector, "email").is_empty());
+
+        let mut corrector = SpellCorrector::new(vec!["e-mail".to_string()], 1)
+            .with_hyphen_insensitive(true)
+            .with_diacritic_insensitive(true);
+        assert_eq!(best(&corrector, "email"), vec![("e-mail".to_string(), 0)]);
+        corrector.add_word_to_dictionary("café-bar");
+        assert_eq!(
+            best(&corrector, "cafebar"),
+            vec![("café-bar".to_string(), 0)]
+        );
+
+        // off: the hyphen is an ordinary character, one edit away
+        let plain = SpellCorrector::new(vec!["email".to_string()], 1);
+        assert_eq!(best(&plain, "e-mail"), vec![("email".to_string(), 1)]);
+    }
+
+    #[test]
+    fn test_cache_keyed_by_suggestion_count() {
+        let dict: Vec<String> = ["spelling", "spilling", "selling", "smelling", "swelling"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict.clone(), 2);
+        let fresh = SpellCorrector::new(dict, 2);
+        let words = |result: SuggestedCorrection| match result {
+            SuggestedCorrection::Suggestions(list) => {
+                list.into_iter().map(|s| s.word).collect::<Vec<_>>()
+            }
+            SuggestedCorrection::NoSuggestions => Vec::new(),
+        };
+        for n in [1, 3, 1, 5, 2, 5, 3, 10, 1] {
+            let cached = words(corrector.suggest_single_word_corrections("speling", n));
+            assert_eq!(cached.len(), n.min(5), "n = {}", n);
+            assert_eq!(
+                cached,
+                words(fresh.clone().suggest_single_word_corrections("speling", n)),
+                "n = {}",
+                n
+            );
+        }
+        assert!(
+            corrector
+                .suggestion_cache()
+                .get(&("speling".to_string(), 3, 2))
+                .is_some()
+        );
+    }
+
+    #[test]
+    fn test_suggest_corrections_sparse() {
+        let dict = vec!["the".to_string(), "cat".to_string(), "sat".to_string()];
+        let corrector = SpellCorrector::new(dict, 1).with_parallel_threshold(2);
+        let words: Vec<String> = ["the", "cst", "sat", "qqqqq", "thw", "cat"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let sparse = corrector.suggest_corrections_sparse(&words, 2);
+        let positions: Vec<usize> = sparse.iter().map(|(i, _)| *i).collect();
+        assert_eq!(positions, vec![1, 4]);
+        assert_eq!(sparse[1].1.best().unwrap().word, "the");
+
+        let dense = corrector.suggest_word_corrections(&words, 2);
+        for (i, result) in &sparse {
+            let words = |r: &SuggestedCorrection| -> Vec<String> {
+                r.suggestions().iter().map(|s| s.word.clone()).collect()
+            };
+            assert_eq!(words(result), words(&dense[*i]));
+        }
+        assert!(corrector.suggest_corrections_sparse(&[], 2).is_empty());
+    }
+
+    #[test]
+    fn test_word_list_with_bom_and_crlf() {
+        let path = std::env::temp_dir().join(format!("spellcheck_bom_{}.txt", std::process::id()));
+        fs::write(&path, "\u{feff}Apple\r\nbanana\r\n\r\ncherry\rdate \r\n").unwrap();
+        let corrector = SpellCorrector::from_word_list_file(path.to_str().unwrap(), 1);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(
+            corrector.dictionary.words,
+            vec!["apple", "banana", "cherry", "date"]
+        );
+        assert!(corrector.contains("apple"));
+        assert!(matches!(
+            corrector.suggest_single_word_corrections("aple", 1),
+            SuggestedCorrection::Suggestions(list) if list[0].word == "apple"
+        ));
+    }
+
+    #[test]
+    fn test_anagrams_of() {
+        let dict: Vec<String> = ["listen", "silent", "enlist", "tinsel", "listens", "lists"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let scanned = SpellCorrector::new(dict, 1);
+        let mut indexed = scanned.clone().with_anagram_index(true);
+        for corrector in [&scanned, &indexed] {
+            assert_eq!(
+                corrector.anagrams_of("listen"),
+                vec!["silent", "enlist", "tinsel"]
+            );
+            assert_eq!(
+                corrector.anagrams_of("inlets"),
+                vec!["listen", "silent", "enlist", "tinsel"]
+            );
+            assert!(corrector.anagrams_of("listed").is_empty());
+        }
+        indexed.add_word_to_dictionary("inlets");
+        assert_eq!(indexed.anagrams_of("silent").len(), 4);
+        assert!(indexed.anagrams_of("silent").contains(&"inlets"));
+    }
+
+    #[test]
+    fn test_abbreviation_expansions() {
+        let dict = vec![
+            "government".to_string(),
+            "street".to_string(),
+            "got".to_string(),
+        ];
         let mut corrector = SpellCorrector::new(dict, 1);
+        corrector.add_expansion("govt", "government");
+        corrector.add_expansion("st", "street");
+        corrector.add_expansion("st", "saint");
 
-        println!(
-            "Res: {:?}",
-            corrector.suggest_single_word_corrections("cart", 2)
+        let (kind, result) = corrector.suggest_with_match_kind("govt", 3);
+        assert_eq!(kind, MatchKind::Abbreviation);
+        let best = result.best().unwrap();
+        assert_eq!(best.word, "government");
+        assert_eq!(best.distance, 6);
+        assert_eq!(result.suggestions().len(), 1);
+
+        let words: Vec<String> = corrector
+            .suggest_single_word_corrections("st", 3)
+            .into_suggestions()
+            .into_iter()
+            .map(|s| s.word)
+            .collect();
+        assert_eq!(words, vec!["street", "saint"]);
+        assert_eq!(
+            corrector
+                .suggest_single_word_corrections("st", 1)
+                .suggestions()
+                .len(),
+            1
         );
-        // "cart" is unknown → only "cat" suggested
-        if let SuggestedCorrection::Suggestions(list) =
-            corrector.suggest_single_word_corrections("cart", 2)
-        {
-            assert_eq!(list[0].word, "cat");
+        assert_eq!(corrector.correct_text("the govt"), "the government");
+
+        // other queries are unaffected
+        let (kind, _) = corrector.suggest_with_match_kind("gots", 3);
+        assert_eq!(kind, MatchKind::Corrected);
+    }
+
+    #[test]
+    fn test_stats_snapshot() {
+        let dict = vec!["ab".to_string(), "ac".to_string()];
+        let corrector = SpellCorrector::new(dict.clone(), 1);
+        let _ = corrector.suggest_single_word_corrections("ad", 2);
+        let _ = corrector.suggest_single_word_corrections("ad", 2);
+        let _ = corrector.suggest_single_word_corrections("ab", 2);
+        // "ab", "a", "b", "ac", "c"
+        assert_eq!(
+            corrector.stats(),
+            CorrectorStats {
+                words: 2,
+                deletion_keys: 5,
+                entries: 6,
+                estimated_bytes: SpellCorrector::estimate_index_bytes(&dict, 1),
+                max_edit_distance: 1,
+                cache_hits: 1,
+                cache_misses: 1,
+            }
+        );
+        let copy = corrector.clone();
+        assert_eq!(copy.stats().cache_hits, 0);
+        assert_eq!(copy.stats().words, 2);
+    }
+
+    #[test]
+    fn test_noisy_channel_ranking() {
+        let words = vec![
+            ("that".to_string(), 1_000_000),
+            ("theta".to_string(), 10),
+            ("thai".to_string(), 1_000),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 2);
+        let ranked = |corrector: &SpellCorrector| -> Vec<String> {
+            corrector
+                .suggest_single_word_corrections("thta", 3)
+                .into_suggestions()
+                .into_iter()
+                .map(|s| s.word)
+                .collect()
+        };
+        // by distance, the one-insertion "theta" wins despite being rare
+        assert_eq!(ranked(&corrector), vec!["theta", "that", "thai"]);
+        // a swap away from a very common word is the likelier typo
+        let noisy = corrector.with_noisy_channel(ErrorModel::default());
+        assert_eq!(ranked(&noisy), vec!["that", "theta", "thai"]);
+        assert_eq!(noisy.correct_text("thta is"), "That is");
+    }
+
+    #[test]
+    fn test_pinned_queries_survive_churn() {
+        let dict: Vec<String> = ["spelling", "spilling", "selling", "swelling"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let mut corrector = SpellCorrector::new(dict, 2);
+        corrector.pin_queries(&["speling".to_string()]);
+
+        for i in 0..500 {
+            let _ = corrector.suggest_single_word_corrections(&format!("spel{}ing", i), 3);
         }
+        let misses = corrector.stats().cache_misses;
+        for n in [1, 4, 2] {
+            let pinned = corrector.suggest_single_word_corrections("speling", n);
+            assert_eq!(pinned.suggestions().len(), n.min(4));
+            assert_eq!(pinned.best().unwrap().word, "spelling");
+        }
+        assert_eq!(corrector.stats().cache_misses, misses);
 
-        // Add "cart", run again – now no suggestions (exact hit)
-        corrector.add_word_to_dictionary("cart");
-        match corrector.suggest_single_word_corrections("cart", 2) {
-            SuggestedCorrection::NoSuggestions => {}
-            _ => panic!("expected no suggestions after adding exact word"),
+        // pinned results follow dictionary changes, recomputed once on the
+        // next lookup however many words were added
+        corrector.add_word_to_dictionary("speeling");
+        corrector.add_word_to_dictionary("spellings");
+        for _ in 0..3 {
+            let words: Vec<String> = corrector
+                .suggest_single_word_corrections("speling", 2)
+                .into_suggestions()
+                .into_iter()
+                .map(|s| s.word)
+                .collect();
+            assert_eq!(words, vec!["speeling", "spelling"]);
+        }
+        assert_eq!(corrector.stats().cache_misses, misses + 1);
+    }
+
+    #[test]
+    fn test_correct_text_with_report() {
+        let dict: Vec<String> = ["the", "quick", "brown", "fox", "jumps"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 2);
+        let text = "Teh quick brwn fox jumps.";
+        let (corrected, changes) = corrector.correct_text_with_report(text);
+        assert_eq!(corrected, corrector.correct_text(text));
+        assert_eq!(
+            changes,
+            vec![
+                Correction {
+                    span: 0..3,
+                    original: "Teh".to_string(),
+                    replacement: "The".to_string(),
+                    distance: Some(2),
+                },
+                Correction {
+                    span: 10..14,
+                    original: "brwn".to_string(),
+                    replacement: "brown".to_string(),
+                    distance: Some(1),
+                },
+            ]
+        );
+        assert_eq!(&text[changes[1].span.clone()], "brwn");
+
+        let (_, changes) = corrector.correct_text_with_report("the quick fox");
+        assert!(changes.is_empty());
+    }
+
+    #[test]
+    fn test_invisible_chars_are_stripped() {
+        let dict = vec!["hyphenation".to_string(), "zero".to_string()];
+        let corrector = SpellCorrector::new(dict.clone(), 1);
+        assert!(corrector.contains("hyphen\u{00AD}ation"));
+        assert!(corrector.contains("\u{200B}zero"));
+        assert!(matches!(
+            corrector.suggest_single_word_corrections("hyphen\u{00AD}ation", 2),
+            SuggestedCorrection::NoSuggestions
+        ));
+        // the text is correct as written, so it is left alone
+        assert_eq!(
+            corrector.correct_text("hyphen\u{00AD}ation"),
+            "hyphen\u{00AD}ation"
+        );
+        let best = corrector.best_correction("hyphen\u{00AD}aton").unwrap();
+        assert_eq!((best.word.as_str(), best.distance), ("hyphenation", 1));
+
+        let exact = SpellCorrector::new(dict, 1).with_invisible_chars(&[]);
+        assert!(!exact.contains("hyphen\u{00AD}ation"));
+        // the two-byte soft hyphen alone puts it out of range
+        assert!(exact.best_correction("hyphen\u{00AD}ation").is_none());
+    }
+
+    #[test]
+    fn test_per_query_max_edit_distance() {
+        let dict: Vec<String> = ["receive", "relieve", "revive", "deceive"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 2);
+        let words = |result: SuggestedCorrection| -> Vec<(String, usize)> {
+            result
+                .into_suggestions()
+                .into_iter()
+                .map(|s| (s.word, s.distance))
+                .collect()
+        };
+        let lenient = words(corrector.suggest_single_word_corrections("recieve", 5));
+        assert_eq!(
+            lenient,
+            vec![
+                ("relieve".to_string(), 1),
+                ("receive".to_string(), 2),
+                ("revive".to_string(), 2),
+            ]
+        );
+        assert_eq!(
+            words(corrector.suggest_with_max_edit_distance("recieve", 5, 2)),
+            lenient
+        );
+        // recieve -> receive is a swap, two edits
+        assert_eq!(
+            words(corrector.suggest_with_max_edit_distance("recieve", 5, 1)),
+            vec![("relieve".to_string(), 1)]
+        );
+        assert!(words(corrector.suggest_with_max_edit_distance("recieve", 5, 0)).is_empty());
+        // a larger distance than configured is capped, and the full query
+        // is unaffected by the strict one's cached result
+        assert_eq!(
+            words(corrector.suggest_with_max_edit_distance("recieve", 5, 9)),
+            lenient
+        );
+        assert_eq!(
+            words(corrector.suggest_single_word_corrections("recieve", 5)),
+            lenient
+        );
+    }
+
+    #[test]
+    fn test_dictionary_diff() {
+        let words = |list: &[&str]| list.iter().map(|s| s.to_string()).collect::<Vec<_>>();
+        let old = SpellCorrector::new(words(&["apple", "banana", "cherry"]), 1);
+        let new = SpellCorrector::new(words(&["cherry", "date", "apple", "elderberry"]), 2);
+        assert_eq!(
+            old.diff(&new),
+            DictionaryDiff {
+                added: words(&["date", "elderberry"]),
+                removed: words(&["banana"]),
+            }
+        );
+        let reverse = new.diff(&old);
+        assert_eq!(reverse.added, words(&["banana"]));
+        assert_eq!(reverse.removed, words(&["date", "elderberry"]));
+        assert_eq!(old.diff(&old.clone()), DictionaryDiff::default());
+    }
+
+    #[test]
+    fn test_deletion_depth_policy() {
+        let dict: Vec<String> = ["cat", "medicine", "internationalization"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let full = SpellCorrector::new(dict.clone(), 2);
+        let shaped = SpellCorrector::new(dict, 2)
+            .with_deletion_depth_policy(|len| if (5..=10).contains(&len) { 2 } else { 1 });
+        let keys =
+            |corrector: &SpellCorrector| corrector.dictionary.dictionary_del_mappings.clone();
+        let (full_keys, shaped_keys) = (keys(&full), keys(&shaped));
+        assert!(shaped_keys.len() < full_keys.len());
+        // short and long words stop at one deletion, medium ones go to two
+        assert!(shaped_keys.contains_key("ct") && !shaped_keys.contains_key("t"));
+        assert!(shaped_keys.contains_key("mdicne"));
+        assert!(shaped_keys.contains_key("nternationalization"));
+        assert!(!shaped_keys.contains_key("nternationalizaion"));
+
+        let best = |corrector: &SpellCorrector, word: &str| {
+            corrector
+                .best_correction(word)
+                .map(|s| (s.word, s.distance))
+        };
+        for corrector in [&full, &shaped] {
+            assert_eq!(best(corrector, "medcne"), Some(("medicine".to_string(), 2)));
+            assert_eq!(
+                best(corrector, "internationalizaton"),
+                Some(("internationalization".to_string(), 1))
+            );
+            assert_eq!(best(corrector, "cst"), Some(("cat".to_string(), 1)));
+        }
+        // two edits from a depth-1 word are out of reach, consistently
+        assert!(best(&full, "internatonalizaton").is_some());
+        assert!(best(&shaped, "internatonalizaton").is_none());
+        assert!(best(&shaped, "cx").is_none());
+
+        let mut shaped = shaped;
+        shaped.add_word_to_dictionary("dog");
+        assert!(!shaped.dictionary.dictionary_del_mappings.contains_key("g"));
+    }
+
+    #[test]
+    fn test_phrase_passed_as_single_word() {
+        let dict: Vec<String> = ["new", "york", "city"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let corrector = SpellCorrector::new(dict, 1);
+        fn suggestions_of(corrector: &SpellCorrector, query: &str) -> Option<Vec<(String, usize)>> {
+            match corrector.suggest_single_word_corrections(query, 3) {
+                SuggestedCorrection::Suggestions(list) => {
+                    Some(list.into_iter().map(|s| (s.word, s.distance)).collect())
+                }
+                SuggestedCorrection::NoSuggestions => None,
+            }
+        }
+        let suggestions = |query: &str| suggestions_of(&corrector, query);
+        assert_eq!(suggestions("new york"), None);
+        assert_eq!(suggestions("new  york\t"), None);
+        assert_eq!(
+            suggestions("nw yrk"),
+            Some(vec![("new york".to_string(), 2)])
+        );
+        assert_eq!(
+            suggestions("new yorkk qqqq"),
+            Some(vec![("new york qqqq".to_string(), 1)])
+        );
+        assert_eq!(suggestions("qqqq zzzz"), Some(Vec::new()));
+
+        // a phrase the dictionary lists is looked up whole
+        let mut corrector = corrector;
+        corrector.add_word_to_dictionary("san francisco");
+        assert!(corrector.contains("san francisco"));
+        assert!(matches!(
+            corrector.suggest_single_word_corrections("san francisco", 3),
+            SuggestedCorrection::NoSuggestions
+        ));
+
+        // and a misspelling of it is found through the index, though "san"
+        // alone has no correction
+        assert_eq!(
+            suggestions_of(&corrector, "san fransisco"),
+            Some(vec![("san francisco".to_string(), 1)])
+        );
+    }
+
+    #[test]
+    fn test_min_frequency_filters_rare_words() {
+        let words = vec![
+            ("speling".to_string(), 1),
+            ("spelling".to_string(), 5_000),
+            ("spewing".to_string(), 40),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 2);
+        let ranked = |corrector: &SpellCorrector, word: &str| -> Vec<String> {
+            corrector
+                .suggest_single_word_corrections(word, 5)
+                .into_suggestions()
+                .into_iter()
+                .map(|s| s.word)
+                .collect()
+        };
+        assert_eq!(
+            ranked(&corrector, "spelin"),
+            vec!["speling", "spelling", "spewing"]
+        );
+
+        let filtered = corrector.with_min_frequency(10);
+        assert_eq!(ranked(&filtered, "spelin"), vec!["spelling", "spewing"]);
+        assert_eq!(ranked(&filtered, "spelng"), vec!["spelling", "spewing"]);
+        // the rare word is still a known word
+        assert!(filtered.contains("speling"));
+        assert!(
+            filtered
+                .suggest_single_word_corrections("speling", 5)
+                .is_correct()
+        );
+    }
+
+    #[test]
+    fn test_min_frequency_applies_to_every_query() {
+        let words = vec![("cart".to_string(), 1), ("carts".to_string(), 100)];
+        let corrector = SpellCorrector::new_with_frequencies(words, 2).with_min_frequency(5);
+        let words_of =
+            |list: Vec<Suggestion>| -> Vec<String> { list.into_iter().map(|s| s.word).collect() };
+        assert_eq!(
+            words_of(
+                corrector
+                    .suggest_single_word_corrections("car", 5)
+                    .into_suggestions()
+            ),
+            vec!["carts"]
+        );
+        assert_eq!(
+            words_of(corrector.suggest_best_tier("car").into_suggestions()),
+            vec!["carts"]
+        );
+        assert_eq!(
+            words_of(
+                corrector
+                    .complete_or_correct("car", 5)
+                    .into_iter()
+                    .map(|(_, s)| s)
+                    .collect()
+            ),
+            vec!["carts"]
+        );
+    }
+
+    #[test]
+    fn test_runon_segmentations() {
+        let words = vec![
+            ("in".to_string(), 50),
+            ("fact".to_string(), 10),
+            ("inf".to_string(), 1),
+            ("act".to_string(), 5),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 1);
+        let segmentations = corrector.runon_segmentations("infakt", 5);
+        let shape: Vec<(&str, &str, usize)> = segmentations
+            .iter()
+            .map(|s| (s.segmented.as_str(), s.corrected.as_str(), s.distance))
+            .collect();
+        assert_eq!(
+            shape,
+            vec![("in fakt", "in fact", 1), ("inf akt", "inf act", 1)]
+        );
+        assert!(segmentations[0].score > segmentations[1].score);
+        let top = &segmentations[0];
+        let expected = 51f64.ln() + 11f64.ln() - BEAM_EDIT_PENALTY;
+        assert!((top.score - expected).abs() < 1e-9);
+        assert_eq!(corrector.runon_segmentations("infakt", 1).len(), 1);
+        assert!(corrector.runon_segmentations("fact", 5).is_empty());
+    }
+
+    #[test]
+    fn test_validate_index() {
+        let words = vec!["hello".to_string(), "help".to_string()];
+        let mut corrector = SpellCorrector::new(words, 1);
+        assert_eq!(corrector.validate_index(), Ok(()));
+
+        let mappings = &mut corrector.dictionary.dictionary_del_mappings;
+        mappings.insert("hlp".to_string(), vec![0, 1]); // fits "help" only
+        mappings.insert("hp".to_string(), vec![1]); // two deletions, indexed to one
+        mappings.insert("elo".to_string(), vec![7]);
+        assert_eq!(
+            corrector.validate_index(),
+            Err(vec![
+                "deletion \"elo\" maps to word 7 of a 2-word dictionary".to_string(),
+                "deletion \"hlp\" is not within 1 deletions of \"hello\"".to_string(),
+                "deletion \"hp\" is not within 1 deletions of \"help\"".to_string(),
+            ])
+        );
+    }
+
+    #[test]
+    fn test_space_edits() {
+        let words = vec![
+            ("a".to_string(), 100),
+            ("lot".to_string(), 20),
+            ("each".to_string(), 30),
+            ("other".to_string(), 25),
+            ("together".to_string(), 10),
+            ("slot".to_string(), 5),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 1);
+        let best = |corrector: &SpellCorrector, word: &str| -> Vec<(String, usize)> {
+            match corrector.suggest_single_word_corrections(word, 5) {
+                SuggestedCorrection::Suggestions(list) => {
+                    list.into_iter().map(|s| (s.word, s.distance)).collect()
+                }
+                SuggestedCorrection::NoSuggestions => Vec::new(),
+            }
+        };
+        // off by default: "alot" is only an edit from "lot" and "slot"
+        assert!(!best(&corrector, "alot").iter().any(|(w, _)| w == "a lot"));
+
+        let corrector = corrector.with_space_edits(true);
+        // missing space
+        assert_eq!(
+            best(&corrector, "eachother"),
+            vec![("each other".to_string(), 1)]
+        );
+        let alot = best(&corrector, "alot");
+        assert!(alot.contains(&("a lot".to_string(), 1)));
+        assert!(alot.contains(&("lot".to_string(), 1)));
+        // extra space
+        assert_eq!(
+            best(&corrector, "togeth er"),
+            vec![("together".to_string(), 1)]
+        );
+        // two correct words are left alone even if they would join into one
+        assert!(best(&corrector, "a lot").is_empty());
+    }
+
+    #[test]
+    fn test_suggestion_padding() {
+        let words = vec![
+            ("hello".to_string(), 10),
+            ("help".to_string(), 5),
+            ("world".to_string(), 8),
+            ("cat".to_string(), 3),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 1);
+        let listed = |corrector: &SpellCorrector| -> Vec<(String, usize)> {
+            match corrector.suggest_single_word_corrections("helo", 3) {
+                SuggestedCorrection::Suggestions(list) => {
+                    list.into_iter().map(|s| (s.word, s.distance)).collect()
+                }
+                SuggestedCorrection::NoSuggestions => Vec::new(),
+            }
+        };
+        // only two words are within one edit
+        let short = listed(&corrector);
+        assert_eq!(
+            short,
+            vec![("hello".to_string(), 1), ("help".to_string(), 1)]
+        );
+
+        let corrector = corrector.with_suggestion_padding(true);
+        let padded = listed(&corrector);
+        assert_eq!(padded.len(), 3);
+        assert_eq!(padded[..2], short[..]);
+        assert_eq!(padded[2], ("world".to_string(), 4));
+
+        // the padded words obey the same limits as searched ones
+        let capped = corrector.clone().with_max_suggestion_len(4);
+        assert_eq!(
+            listed(&capped),
+            vec![("help".to_string(), 1), ("cat".to_string(), 4)]
+        );
+        let guarded = corrector.clone().with_length_ratio_guard(0.9, 1.1);
+        assert_eq!(listed(&guarded), short);
+
+        // pinned answers are padded too
+        let mut pinned = corrector.clone();
+        pinned.pin_queries(&["helo".to_string()]);
+        assert_eq!(listed(&pinned), padded);
+    }
+
+    #[test]
+    fn test_query_deletion_variants() {
+        let words = vec!["cat".to_string(), "at".to_string(), "cart".to_string()];
+        let corrector = SpellCorrector::new(words, 1);
+        let variants = corrector.query_deletion_variants("cta");
+        let expected: HashSet<String> = ["ta", "ca", "ct"].iter().map(|s| s.to_string()).collect();
+        assert_eq!(variants, expected);
+        // "ca" is two deletions from "cart", beyond the indexed depth
+        assert_eq!(corrector.deletion_key_words("ca"), vec!["cat".to_string()]);
+        assert_eq!(
+            corrector.deletion_key_words("at"),
+            vec!["cat".to_string(), "at".to_string()]
+        );
+        assert!(corrector.deletion_key_words("ta").is_empty());
+    }
+
+    #[test]
+    fn test_query_itself_is_a_deletion_key() {
+        // "cat" is "cart" with one deletion, so at distance 1 the only key
+        // linking them is the unmodified query
+        let corrector = SpellCorrector::new(vec!["cart".to_string()], 1);
+        match corrector.suggest_single_word_corrections("cat", 5) {
+            SuggestedCorrection::Suggestions(list) => {
+                assert_eq!(list.len(), 1);
+                assert_eq!((list[0].word.as_str(), list[0].distance), ("cart", 1));
+            }
+            SuggestedCorrection::NoSuggestions => panic!("expected suggestions"),
+        }
+        let trace = corrector.trace("cat", 5);
+        assert_eq!(trace.suggestions.len(), 1);
+        assert_eq!(trace.suggestions[0].matched_key, "cat");
+    }
+
+    #[test]
+    fn test_case_insensitive_merges_case_variants() {
+        let words = vec![
+            ("Hello".to_string(), 3),
+            ("help".to_string(), 1),
+            ("hello".to_string(), 4),
+        ];
+        let corrector = SpellCorrector::new_with_frequencies(words, 1).with_case_insensitive(true);
+        assert_eq!(corrector.dictionary.words, vec!["hello", "help"]);
+        assert_eq!(corrector.dictionary.frequencies, vec![7, 1]);
+        match corrector.suggest_single_word_corrections("Helo", 5) {
+            SuggestedCorrection::Suggestions(list) => {
+                let found: Vec<(&str, u64)> = list
+                    .iter()
+                    .map(|s| (s.word.as_str(), s.frequency))
+                    .collect();
+                assert_eq!(found, vec![("hello", 7), ("help", 1)]);
+            }
+            SuggestedCorrection::NoSuggestions => panic!("expected suggestions"),
         }
     }
+
+    #[test]
+    fn test_suggest_progressive_matches_plain_query_settings() {
+        let words = vec![
+            ("spell".to_string(), 50),
+            ("spelt".to_string(), 1),
+            ("spill".to_string(), 20),
+        ];
+        let mut corrector = SpellCorrector::new_with_frequencies(words, 2).with_min_frequency(10);
+        corrector.add_correction_override("speling", "spelling");
+        let progressive = |corrector: &SpellCorrector, word: &str| {
+            let mut tiers: Vec<(usize, Vec<String>)> = Vec::new();
+            let result = corrector.suggest_progressive(word, 5, |distance, tier| {
+                tiers.push((distance, tier.iter().map(|s| s.word.clone()).collect()));
+            });
+            let words: Vec<String> = result
+                .into_suggestions()
+                .into_iter()
+                .map(|s| s.word)
+                .collect();
+            (tiers, words)
+        };
+        let plain = |corrector: &SpellCorrector, word: &str| -> Vec<String> {
+            corrector
+                .suggest_single_word_corrections(word, 5)
+                .into_suggestions()
+                .into_iter()
+                .map(|s| s.word)
+                .collect()
+        };
+
+        // the override answers at once, as one tier
+        let (tiers, words) = progressive(&corrector, "speling");
+        assert_eq!(tiers, vec![(1, vec!["spelling".to_string()])]);
+        assert_eq!(words, plain(&corrector, "speling"));
+        // the rare "spelt" is filtered from the tiers as from the plain list
+        let (tiers, words) = progressive(&corrector, "spel");
+        assert_eq!(
+            tiers,
+            vec![
+                (1, vec!["spell".to_string()]),
+                (2, vec!["spill".to_string()])
+            ]
+        );
+        assert_eq!(words, plain(&corrector, "spel"));
+    }
 }
@@ -0,0 +1,325 @@
+use std::borrow::Cow;
+use std::collections::HashSet;
+
+/// A run of text with its byte span in the source string.
+///
+/// `tokenize` produces alternating whitespace and non-whitespace tokens that
+/// together cover the input exactly, so text can be rebuilt byte for byte.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Token<'a> {
+    pub text: &'a str,
+    pub start: usize, // byte offset of the first byte
+    pub end: usize,   // byte offset one past the last byte
+    pub is_whitespace: bool,
+}
+
+/// Splits `text` into maximal runs of whitespace and non-whitespace.
+pub fn tokenize(text: &str) -> Vec<Token<'_>> {
+    let mut tokens = Vec::new();
+    let mut start = 0;
+    let mut in_whitespace = None;
+    for (i, c) in text.char_indices() {
+        let is_whitespace = c.is_whitespace();
+        match in_whitespace {
+            Some(prev) if prev != is_whitespace => {
+                tokens.push(Token {
+                    text: &text[start..i],
+                    start,
+                    end: i,
+                    is_whitespace: prev,
+                });
+                start = i;
+            }
+            _ => {}
+        }
+        in_whitespace = Some(is_whitespace);
+    }
+    if let Some(is_whitespace) = in_whitespace {
+        tokens.push(Token {
+            text: &text[start..],
+            start,
+            end: text.len(),
+            is_whitespace,
+        });
+    }
+    tokens
+}
+
+/// Splits a non-whitespace token into leading punctuation, the word, and
+/// trailing punctuation, e.g. `"(hello),"` into `("(", "hello", "),")`.
+///
+/// The word is empty when the token has no alphanumeric characters, in which
+/// case the whole token is returned as the prefix.
+pub fn split_punctuation(token: &str) -> (&str, &str, &str) {
+    let word = token.trim_matches(|c: char| !c.is_alphanumeric());
+    if word.is_empty() {
+        return (token, "", "");
+    }
+    let start = token.len()
+        - token
+            .trim_start_matches(|c: char| !c.is_alphanumeric())
+            .len();
+    let end = start + word.len();
+    (&token[..start], word, &token[end..])
+}
+
+/// Contractions whose apostrophes are part of the word, see
+/// `split_punctuation_with_contractions`.
+pub const DEFAULT_CONTRACTIONS: &[&str] = &[
+    "'bout", "'cause", "'em", "'n'", "'round", "'til", "'tis", "'twas", "ol'",
+];
+
+/// Like `split_punctuation`, but keeps the apostrophes of known
+/// contractions: if the word together with the apostrophe before and/or
+/// after it is in `contractions` (lowercase, with straight apostrophes), the
+/// apostrophes stay in the word, so `"'Tis,"` splits into `("", "'Tis",
+/// ",")`. Any other quote is stripped as usual, so the possessive `"dogs'"`
+/// still gives `"dogs"`.
+pub fn split_punctuation_with_contractions<'a>(
+    token: &'a str,
+    contractions: &HashSet<String>,
+) -> (&'a str, &'a str, &'a str) {
+    let (prefix, word, suffix) = split_punctuation(token);
+    if word.is_empty() || contractions.is_empty() {
+        return (prefix, word, suffix);
+    }
+    let apostrophe_len = |c: Option<char>| match c {
+        Some(c @ ('\'' | '\u{2019}')) => c.len_utf8(),
+        _ => 0,
+    };
+    let start = prefix.len();
+    let end = start + word.len();
+    let lead = apostrophe_len(prefix.chars().next_back());
+    let trail = apostrophe_len(suffix.chars().next());
+    for (s, e) in [
+        (start - lead, end + trail),
+        (start - lead, end),
+        (start, end + trail),
+    ] {
+        if (s, e) == (start, end) {
+            continue;
+        }
+        let candidate = token[s..e].to_lowercase().replace('\u{2019}', "'");
+        if contractions.contains(&candidate) {
+            return (&token[..s], &token[s..e], &token[e..]);
+        }
+    }
+    (prefix, word, suffix)
+}
+
+/// Unit suffixes `is_numeric_token` accepts after a number, including
+/// ordinal endings like the "th" of "4th".
+pub const DEFAULT_UNITS: &[&str] = &[
+    "b", "c", "cm", "d", "f", "ft", "g", "gb", "ghz", "h", "hz", "in", "k", "kb", "kg", "khz",
+    "km", "kmh", "kph", "kw", "l", "lb", "lbs", "m", "mb", "mg", "mhz", "mi", "min", "ml", "mm",
+    "mph", "ms", "nd", "oz", "rd", "s", "st", "tb", "th", "v", "w", "x", "yd",
+];
+
+/// Whether `text` is a number, optionally signed and followed by `%` or by
+/// one of `units` (matched ignoring case): "42", "-3.14", "1,000", "20%",
+/// "50km". Separators must sit between digits, so "1..2" and "3." are not
+/// numbers.
+pub fn is_numeric_token(text: &str, units: &HashSet<String>) -> bool {
+    let rest = text.strip_prefix(['+', '-']).unwrap_or(text);
+    let number_len = rest
+        .find(|c: char| !(c.is_ascii_digit() || c == '.' || c == ','))
+        .unwrap_or(rest.len());
+    let (number, unit) = rest.split_at(number_len);
+    let well_formed = number.starts_with(|c: char| c.is_ascii_digit())
+        && number.ends_with(|c: char| c.is_ascii_digit())
+        && !number.contains("..")
+        && !number.contains(",,")
+        && !number.contains(".,")
+        && !number.contains(",.");
+    well_formed && (unit.is_empty() || unit == "%" || units.contains(&unit.to_lowercase()))
+}
+
+/// Splits a code identifier into subwords and the separators between them,
+/// e.g. "getUserName" into "get", "User", "Name" and "user_count" into
+/// "user", "_", "count". Subwords are runs of letters, split where a
+/// lowercase letter is followed by an uppercase one and before the last
+/// capital of an acronym ("HTTPServer" gives "HTTP", "Server"); anything
+/// else (underscores, hyphens, digits) forms separator pieces. The pieces
+/// concatenate back to `identifier`.
+pub fn split_identifier(identifier: &str) -> Vec<&str> {
+    let chars: Vec<(usize, char)> = identifier.char_indices().collect();
+    let mut pieces = Vec::new();
+    let mut start = 0;
+    for i in 1..chars.len() {
+        let (pos, c) = chars[i];
+        let prev = chars[i - 1].1;
+        let next = chars.get(i + 1).map(|&(_, c)| c);
+        let boundary = prev.is_alphabetic() != c.is_alphabetic()
+            || (prev.is_lowercase() && c.is_uppercase())
+            || (prev.is_uppercase() && c.is_uppercase() && next.is_some_and(char::is_lowercase));
+        if boundary {
+            pieces.push(&identifier[start..pos]);
+            start = pos;
+        }
+    }
+    if start < identifier.len() {
+        pieces.push(&identifier[start..]);
+    }
+    pieces
+}
+
+/// Characters that do not render but break exact matches, stripped from
+/// queries by default: the soft hyphen (U+00AD), zero-width space (U+200B),
+/// zero-width non-joiner and joiner (U+200C, U+200D), word joiner (U+2060)
+/// and the zero-width no-break space / byte order mark (U+FEFF). Text copied
+/// from PDFs and web pages often carries them inside words.
+pub const DEFAULT_INVISIBLE_CHARS: &[char] = &[
+    '\u{00AD}', '\u{200B}', '\u{200C}', '\u{200D}', '\u{2060}', '\u{FEFF}',
+];
+
+/// `word` without any of `chars`, borrowed if it contains none of them.
+pub fn strip_invisible<'a>(word: &'a str, chars: &[char]) -> Cow<'a, str> {
+    if word.contains(chars) {
+        Cow::Owned(word.chars().filter(|c| !chars.contains(c)).collect())
+    } else {
+        Cow::Borrowed(word)
+    }
+}
+
+/// `word` with accents removed: combining marks (U+0300 to U+036F) are
+/// dropped and precomposed Latin letters such as "é" or "ł" become their
+/// base letter, so "Résumé" becomes "Resume". Not a full Unicode
+/// decomposition; letters outside Latin-1 and Latin Extended-A are kept.
+pub fn strip_diacritics(word: &str) -> Cow<'_, str> {
+    let is_marked = |c: char| ('\u{300}'..='\u{36f}').contains(&c) || base_letter(c).is_some();
+    if !word.chars().any(is_marked) {
+        return Cow::Borrowed(word);
+    }
+    Cow::Owned(
+        word.chars()
+            .filter(|c| !('\u{300}'..='\u{36f}').contains(c))
+            .map(|c| base_letter(c).unwrap_or(c))
+            .collect(),
+    )
+}
+
+fn base_letter(c: char) -> Option<char> {
+    if c.is_uppercase() {
+        let lower = c.to_lowercase().next()?;
+        return base_letter(lower).and_then(|b| b.to_uppercase().next());
+    }
+    let base = match c {
+        'à'..='å' | 'ā' | 'ă' | 'ą' => 'a',
+        'ç' | 'ć' | 'ĉ' | 'ċ' | 'č' => 'c',
+        'ď' | 'đ' => 'd',
+        'è'..='ë' | 'ē' | 'ĕ' | 'ė' | 'ę' | 'ě' => 'e',
+        'ĝ' | 'ğ' | 'ġ' | 'ģ' => 'g',
+        'ĥ' | 'ħ' => 'h',
+        'ì'..='ï' | 'ĩ' | 'ī' | 'ĭ' | 'į' => 'i',
+        'ĵ' => 'j',
+        'ķ' => 'k',
+        'ĺ' | 'ļ' | 'ľ' | 'ŀ' | 'ł' => 'l',
+        'ñ' | 'ń' | 'ņ' | 'ň' => 'n',
+        'ò'..='ö' | 'ø' | 'ō' | 'ŏ' | 'ő' => 'o',
+        'ŕ' | 'ŗ' | 'ř' => 'r',
+        'ś' | 'ŝ' | 'ş' | 'š' => 's',
+        'ţ' | 'ť' | 'ŧ' => 't',
+        'ù'..='ü' | 'ũ' | 'ū' | 'ŭ' | 'ů' | 'ű' | 'ų' => 'u',
+        'ŵ' => 'w',
+        'ý' | 'ÿ' | 'ŷ' => 'y',
+        'ź' | 'ż' | 'ž' => 'z',
+        _ => return None,
+    };
+    Some(base)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tokenize_round_trips() {
+        let text = "  Hello,\tworld!\n\nsecond  line ";
+        let tokens = tokenize(text);
+        let rebuilt: String = tokens.iter().map(|t| t.text).collect();
+        assert_eq!(rebuilt, text);
+
+        let words: Vec<&str> = tokens
+            .iter()
+            .filter(|t| !t.is_whitespace)
+            .map(|t| t.text)
+            .collect();
+        assert_eq!(words, vec!["Hello,", "world!", "second", "line"]);
+        for token in &tokens {
+            assert_eq!(&text[token.start..token.end], token.text);
+        }
+        assert!(tokenize("").is_empty());
+    }
+
+    #[test]
+    fn test_split_punctuation() {
+        assert_eq!(split_punctuation("(hello),"), ("(", "hello", "),"));
+        assert_eq!(split_punctuation("word"), ("", "word", ""));
+        assert_eq!(split_punctuation("--"), ("--", "", ""));
+    }
+
+    #[test]
+    fn test_split_punctuation_with_contractions() {
+        let contractions: HashSet<String> =
+            DEFAULT_CONTRACTIONS.iter().map(|c| c.to_string()).collect();
+        let split = |token| split_punctuation_with_contractions(token, &contractions);
+        assert_eq!(split("'Tis,"), ("", "'Tis", ","));
+        assert_eq!(
+            split("\u{2018}\u{2019}twas"),
+            ("\u{2018}", "\u{2019}twas", "")
+        );
+        assert_eq!(split("'n'"), ("", "'n'", ""));
+        assert_eq!(split("ol'"), ("", "ol'", ""));
+        // possessives and quotes are still stripped
+        assert_eq!(split("dogs'"), ("", "dogs", "'"));
+        assert_eq!(split("'hello'"), ("'", "hello", "'"));
+        assert_eq!(
+            split_punctuation_with_contractions("'tis", &HashSet::new()),
+            ("'", "tis", "")
+        );
+    }
+
+    #[test]
+    fn test_is_numeric_token() {
+        let units: HashSet<String> = DEFAULT_UNITS.iter().map(|u| u.to_string()).collect();
+        for token in [
+            "42",
+            "50km",
+            "50KM",
+            "3.14",
+            "20%",
+            "-3.5",
+            "1,000,000",
+            "4th",
+        ] {
+            assert!(is_numeric_token(token, &units), "{}", token);
+        }
+        for token in ["km", "50zz", "3.", ".5", "1..2", "20%%", "", "-", "abc"] {
+            assert!(!is_numeric_token(token, &units), "{}", token);
+        }
+        assert!(!is_numeric_token("50km", &HashSet::new()));
+    }
+
+    #[test]
+    fn test_strip_diacritics() {
+        assert_eq!(strip_diacritics("Résumé"), "Resume");
+        assert_eq!(strip_diacritics("naïve"), "naive");
+        assert_eq!(strip_diacritics("Łódź"), "Lodz");
+        // decomposed: "e" followed by a combining acute accent
+        assert_eq!(strip_diacritics("cafe\u{301}"), "cafe");
+        assert!(matches!(strip_diacritics("plain"), Cow::Borrowed("plain")));
+    }
+
+    #[test]
+    fn test_split_identifier() {
+        assert_eq!(split_identifier("getUserName"), vec!["get", "User", "Name"]);
+        assert_eq!(split_identifier("user_count"), vec!["user", "_", "count"]);
+        assert_eq!(split_identifier("HTTPServer2"), vec!["HTTP", "Server", "2"]);
+        assert_eq!(
+            split_identifier("MAX_RETRY__COUNT"),
+            vec!["MAX", "_", "RETRY", "__", "COUNT"]
+        );
+        assert_eq!(split_identifier("x"), vec!["x"]);
+        assert!(split_identifier("").is_empty());
+    }
+}
@@ -0,0 +1,9 @@
+#![deny(unused_must_use)]
+
+use spellcheck::SpellCorrector;
+
+fn main() {
+    let corrector = SpellCorrector::new(vec!["hello".to_string()], 1);
+    corrector.suggest_single_word_corrections("helo", 1);
+    corrector.best_correction("helo");
+}